@@ -1,8 +1,19 @@
-use clap::{command, Parser, Subcommand};
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use chrono::Local;
+
+use crate::models::entry::ProviderConfig;
+use crate::models::log::Level;
+use crate::process::log_query::{LogFilter, parse_time};
 
 #[derive(Parser)]
 #[command(name = "domainhdlr", about = "Cli service to handle domains on Duckdns", author = "PTechSoftware - Ignacio Perez")]
 pub struct Cli {
+    /// Ruta alternativa al archivo de settings
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
     #[command(subcommand)]
     pub command : Commands
 }
@@ -26,24 +37,167 @@ pub enum Commands{
     Status,
     #[command(name="restart", alias="rs")]
     Restart,
+    /// Releer settings y dominios sin reiniciar
+    #[command(name="reload", alias="rl")]
+    Reload,
     #[command(name = "enable-on-boot", alias = "b")]
     EnableOnBoot {
         #[arg(long, short)] activate: bool,
     },
+    /// Ver el log de eventos, incluidos los archivos rotados
     #[command(name="view-log", alias="l")]
-    ViewLog,
+    ViewLog {
+        #[command(flatten)]
+        query: LogArgs,
+    },
     #[command(name="add-domain", alias="ad")]
     AddDomain{
         #[arg(long, short)] name: String,
         #[arg(long, short)] token: String,
         #[arg(long, short)] activated: Option<bool>,
-        #[arg(long, short)] txt: Option<String>,
+        #[arg(long, short = 'x')] txt: Option<String>,
+        /// Publicar también un registro AAAA con la IPv6 pública
+        #[arg(long)] ipv6: bool,
+        /// No publicar registro A (hosts solo IPv6)
+        #[arg(long)] no_ipv4: bool,
+        /// Etiqueta para rutear sus alertas (se puede repetir)
+        #[arg(long = "tag")] tags: Vec<String>,
+        #[command(flatten)]
+        provider: ProviderArgs,
     },
     #[command(name="delete-domain", alias="dd")]
     DeleteDomain {
         #[arg(long, short)] name: String,
     },
     #[command(name="list-domain", alias="ld")]
-    ListDomain
+    ListDomain,
+    /// Ver la configuración y el historial de actualizaciones de un dominio
+    #[command(name="domain-status", alias="ds")]
+    DomainStatus {
+        name: String,
+    },
+    #[command(name="set-txt", alias="txt")]
+    SetTxt {
+        #[arg(long, short)] name: String,
+        #[arg(long, short)] value: String,
+    },
+    #[command(name="clear-txt", alias="ctxt")]
+    ClearTxt {
+        #[arg(long, short)] name: String,
+    },
+
+}
 
-}
\ No newline at end of file
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ProviderKind {
+    Duckdns,
+    Cloudflare,
+    Dyndns2,
+    Rfc2136,
+}
+
+// Opciones específicas del proveedor para `add-domain`.
+// `--token` es el token de DuckDNS, el API token de Cloudflare,
+// la contraseña de dyndns2 o el secreto TSIG (base64) de rfc2136.
+#[derive(Args)]
+pub struct ProviderArgs {
+    #[arg(long, value_enum, default_value = "duckdns")]
+    pub provider: ProviderKind,
+    /// Zona (cloudflare, rfc2136), ej. example.com
+    #[arg(long)]
+    pub zone: Option<String>,
+    /// Pasar el tráfico por el proxy de Cloudflare
+    #[arg(long)]
+    pub proxied: bool,
+    /// TTL del registro en segundos
+    #[arg(long)]
+    pub ttl: Option<u32>,
+    /// Servidor dyndns2 (https://dynupdate.no-ip.com) o rfc2136 (ns1.example.com:53)
+    #[arg(long)]
+    pub server: Option<String>,
+    /// Usuario dyndns2
+    #[arg(long)]
+    pub username: Option<String>,
+    /// Nombre de la clave TSIG (rfc2136)
+    #[arg(long)]
+    pub key_name: Option<String>,
+    /// Algoritmo TSIG (rfc2136)
+    #[arg(long, default_value = "hmac-sha256")]
+    pub algorithm: String,
+}
+
+impl ProviderArgs {
+    pub fn to_config(&self, token: &str) -> Result<ProviderConfig, String> {
+        match self.provider {
+            ProviderKind::Duckdns => Ok(ProviderConfig::DuckDns { token: token.to_string() }),
+            ProviderKind::Cloudflare => Ok(ProviderConfig::Cloudflare {
+                api_token: token.to_string(),
+                zone: self.zone.clone().ok_or("--zone is required for cloudflare")?,
+                proxied: self.proxied,
+                ttl: self.ttl.unwrap_or(1),
+                api_base: None,
+            }),
+            ProviderKind::Dyndns2 => Ok(ProviderConfig::DynDns2 {
+                server: self.server.clone().ok_or("--server is required for dyndns2")?,
+                username: self.username.clone().ok_or("--username is required for dyndns2")?,
+                password: token.to_string(),
+            }),
+            ProviderKind::Rfc2136 => Ok(ProviderConfig::Rfc2136 {
+                server: self.server.clone().ok_or("--server is required for rfc2136")?,
+                zone: self.zone.clone().ok_or("--zone is required for rfc2136")?,
+                key_name: self.key_name.clone().ok_or("--key-name is required for rfc2136")?,
+                key_secret: token.to_string(),
+                algorithm: self.algorithm.clone(),
+                ttl: self.ttl.unwrap_or(300),
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+// Filtros de `view-log`.
+#[derive(Args)]
+pub struct LogArgs {
+    /// Nivel mínimo: debug, info, warn o error
+    #[arg(long)]
+    pub level: Option<Level>,
+    /// Solo eventos de este dominio
+    #[arg(long)]
+    pub domain: Option<String>,
+    /// Solo eventos de este ciclo del loop (ej. ab12cd34)
+    #[arg(long)]
+    pub cycle: Option<String>,
+    /// Desde: 30m, 2h, 7d, 2026-10-13 o "2026-10-13 18:30"
+    #[arg(long)]
+    pub since: Option<String>,
+    /// Hasta, con el mismo formato que --since
+    #[arg(long)]
+    pub until: Option<String>,
+    /// Mostrar solo los últimos N eventos
+    #[arg(short = 'n', long)]
+    pub lines: Option<usize>,
+    /// Seguir mostrando eventos nuevos a medida que llegan
+    #[arg(short, long)]
+    pub follow: bool,
+    #[arg(long, value_enum, default_value = "text")]
+    pub output: OutputFormat,
+}
+
+impl LogArgs {
+    pub fn to_filter(&self) -> Result<LogFilter, String> {
+        let now = Local::now();
+        let time = |s: &Option<String>| s.as_deref().map(|s| parse_time(s, now)).transpose();
+        Ok(LogFilter {
+            level: self.level,
+            domain: self.domain.clone(),
+            cycle: self.cycle.clone(),
+            since: time(&self.since)?,
+            until: time(&self.until)?,
+        })
+    }
+}
@@ -1,40 +1,66 @@
 use clap::Parser;
-use commands::{Cli, Commands};
+use commands::{Cli, Commands, LogArgs, OutputFormat};
 use process::{
-    domains::{add_domain, delete_domain, list_domains},
-    logger::read_log_errors,
+    domains::{add_domain, delete_domain, list_domains, set_domain_txt},
+    log_query::{follow, read_events},
+    logger::Event,
     ubuntu_service::{install_service, set_enable_on_boot, uninstall_service},
 };
-use service::{start, status, stop};
-use std::thread;
+use process::file_lock::{EXIT_ALREADY_RUNNING, LockError};
+use process::rutas::{set_settings_file, use_system_dirs};
+use process::settings::{configured_run_as, load_settings};
+use service::{domain_status, reload, restart, start, status, stop};
+use models::entry::{Entry, ProviderConfig};
+use models::settings::Settings;
+use process::daemon::{Forked, daemonize, wait_ready};
 use tokio::runtime::Runtime;
 mod commands;
 mod models;
 mod process;
 mod service;
 
-#[tokio::main]
-async fn main() {
-
-    let mail_cfg = process::notifier::MailConfig {
-        smtp_server: "smtp.gmail.com".into(),
-        smtp_port: 587,
-        sender: "ptechsoftware.correo@gmail.com".into(),
-        password: "gpoo gqqz cbjq jqzc".into(),
-        recipient: "nachopp98@gmail.com".into(),
-    };
+fn main() {
     let cli = Cli::parse();
-    match cli.command {
-        Commands::Start { detached } => {
-            if detached {
-                thread::spawn(|| {
-                    let rt = Runtime::new().expect("Failed to create Tokio runtime");
-                    if let Err(e) = rt.block_on(start()) {
-                        eprintln!("Error running detached service: {}", e);
-                    }
-                });
-            } else if let Err(e) = start().await {
+    if let Some(path) = &cli.config {
+        // El daemon hace chdir("/"): la ruta tiene que ser absoluta
+        set_settings_file(std::path::absolute(path).unwrap_or(path.clone()));
+    }
+    // Con `run_as` el servicio usa directorios de sistema; la CLI tiene
+    // que buscar ahí el socket, los dominios y los logs
+    if configured_run_as().is_some() {
+        use_system_dirs();
+    }
+
+    // El fork va antes de crear el runtime: con hilos no es seguro.
+    // Los settings se leen antes de cambiar de usuario (`run_as`)
+    let mut preloaded = None;
+    if let Commands::Start { detached: true } = cli.command {
+        let settings = settings_or_exit();
+        match daemonize(&settings) {
+            Ok(Forked::Parent(ready)) => std::process::exit(wait_ready(ready)),
+            Ok(Forked::Daemon) => {
+                process::logger::set_detached();
+                preloaded = Some(settings);
+            }
+            Err(e) => {
+                eprintln!("Error daemonizing: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    process::log_layer::init();
+    let rt = Runtime::new().expect("Failed to create Tokio runtime");
+    rt.block_on(run(cli.command, preloaded));
+}
+
+async fn run(command: Commands, preloaded: Option<Settings>) {
+    match command {
+        Commands::Start { .. } => {
+            let settings = preloaded.unwrap_or_else(settings_or_exit);
+            if let Err(e) = start(&settings).await {
                 eprintln!("Error starting service: {}", e);
+                std::process::exit(exit_code(e.as_ref()));
             }
         }
         Commands::Install => {
@@ -47,41 +73,111 @@ async fn main() {
             _ = set_enable_on_boot(activate);
         }
         Commands::Stop => {
-            stop(&mail_cfg).await.unwrap();
+            if let Err(e) = stop(&settings_or_exit()).await {
+                eprintln!("Error stopping service: {}", e);
+                std::process::exit(1);
+            }
         }
         Commands::Status => {
-            status().unwrap();
+            status().await.unwrap();
         }
         Commands::Restart => {
-            stop(&mail_cfg).await.unwrap();
-            let _ = start();
+            if let Err(e) = restart(&settings_or_exit()).await {
+                eprintln!("Error restarting service: {}", e);
+                std::process::exit(exit_code(e.as_ref()));
+            }
+        }
+        Commands::Reload => {
+            if let Err(e) = reload().await {
+                eprintln!("Error reloading service: {}", e);
+                std::process::exit(1);
+            }
         }
         Commands::AddDomain {
             name,
             token,
             activated,
             txt,
-        } => {
-            add_domain(&name, &token, activated, txt);
-        }
+            ipv6,
+            no_ipv4,
+            tags,
+            provider,
+        } => match provider.to_config(&token) {
+            Ok(_) if no_ipv4 && !ipv6 => eprintln!("--no-ipv4 requires --ipv6"),
+            Ok(ProviderConfig::DuckDns { .. }) if no_ipv4 => {
+                eprintln!("DuckDNS always publishes an A record; --no-ipv4 is not supported")
+            }
+            Ok(cfg) => add_domain(Entry {
+                name,
+                activated: activated.unwrap_or(true),
+                txt,
+                provider: cfg,
+                ipv4: !no_ipv4,
+                ipv6,
+                tags,
+            }),
+            Err(e) => eprintln!("{}", e),
+        },
         Commands::DeleteDomain { name } => {
             delete_domain(&name);
         }
         Commands::ListDomain => {
             list_domains();
         }
-        Commands::ViewLog => {
-            let l = read_log_errors();
-            match l {
-                Ok(d) => {
-                    for el in d {
-                        println!("{}", el)
-                    }
-                }
-                _ => {
-                    println!("Failed retrive logs")
-                }
+        Commands::DomainStatus { name } => {
+            if let Err(e) = domain_status(&name).await {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::SetTxt { name, value } => {
+            set_domain_txt(&name, Some(&value), settings_or_exit().request_timeout()).await;
+        }
+        Commands::ClearTxt { name } => {
+            set_domain_txt(&name, None, settings_or_exit().request_timeout()).await;
+        }
+        Commands::ViewLog { query } => {
+            if let Err(e) = view_log(&query).await {
+                eprintln!("Failed to read logs: {}", e);
+                std::process::exit(1);
             }
         }
     }
 }
+
+/// `view-log`: eventos filtrados, opcionalmente solo los últimos N y
+/// siguiendo el archivo.
+async fn view_log(query: &LogArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let filter = query.to_filter()?;
+    let print = |event: &Event| match query.output {
+        OutputFormat::Text => println!("{}", event),
+        OutputFormat::Json => println!("{}", serde_json::to_string(event).unwrap_or_default()),
+    };
+
+    let (events, position) = read_events(&filter)?;
+    let skip = query.lines.map_or(0, |n| events.len().saturating_sub(n));
+    events[skip..].iter().for_each(print);
+    if query.follow {
+        follow(&filter, position, print).await?;
+    }
+    Ok(())
+}
+
+/// Código de salida para un error de `start`.
+fn exit_code(e: &(dyn std::error::Error + 'static)) -> i32 {
+    match e.downcast_ref::<LockError>() {
+        Some(LockError::AlreadyRunning(_)) => EXIT_ALREADY_RUNNING,
+        _ => 1,
+    }
+}
+
+/// Carga los settings o termina el proceso si el archivo es inválido.
+fn settings_or_exit() -> Settings {
+    match load_settings() {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Error loading settings: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -2,12 +2,170 @@ use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
+/// Proveedor DNS de un dominio, con sus credenciales específicas.
+/// Las entradas sin `provider` se toman como DuckDNS.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ProviderConfig {
+    DuckDns {
+        /// Vacío en archivos viejos: se completa con `token` de la entrada.
+        #[serde(default)]
+        token: String,
+    },
+    Cloudflare {
+        api_token: String,
+        /// Zona que contiene el registro, ej. "example.com".
+        zone: String,
+        #[serde(default)]
+        proxied: bool,
+        /// TTL en segundos; 1 es "automático" en Cloudflare.
+        #[serde(default = "default_cloudflare_ttl")]
+        ttl: u32,
+        /// Base alternativa de la API (pruebas o proxies).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        api_base: Option<String>,
+    },
+    /// Protocolo dyndns2 con autenticación básica.
+    DynDns2 {
+        /// Base del endpoint, ej. "https://dynupdate.no-ip.com".
+        server: String,
+        username: String,
+        password: String,
+    },
+    /// RFC 2136 firmado con TSIG contra un servidor propio.
+    Rfc2136 {
+        /// Servidor primario, ej. "ns1.example.org" o "192.0.2.53:53".
+        server: String,
+        zone: String,
+        key_name: String,
+        /// Secreto TSIG en base64.
+        key_secret: String,
+        #[serde(default = "default_tsig_algorithm")]
+        algorithm: String,
+        #[serde(default = "default_rfc2136_ttl")]
+        ttl: u32,
+    },
+}
+
+fn default_tsig_algorithm() -> String {
+    "hmac-sha256".into()
+}
+
+fn default_rfc2136_ttl() -> u32 {
+    300
+}
+
+fn default_cloudflare_ttl() -> u32 {
+    1
+}
+
+impl ProviderConfig {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProviderConfig::DuckDns { .. } => "duckdns",
+            ProviderConfig::Cloudflare { .. } => "cloudflare",
+            ProviderConfig::DynDns2 { .. } => "dyndns2",
+            ProviderConfig::Rfc2136 { .. } => "rfc2136",
+        }
+    }
+
+    /// Registro detrás del proxy de Cloudflare: resuelve a IPs de su borde,
+    /// no a la publicada.
+    pub fn is_proxied(&self) -> bool {
+        matches!(self, ProviderConfig::Cloudflare { proxied: true, .. })
+    }
+
+    /// Servidor que tiene la zona, si la configuración lo dice; permite
+    /// distinguir caches viejos de una actualización que no se aplicó.
+    pub fn authoritative_server(&self) -> Option<&str> {
+        match self {
+            ProviderConfig::Rfc2136 { server, .. } => Some(server),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "EntryFile")]
 pub struct Entry {
     pub name: String,
-    pub token: String,
     pub activated: bool,
     pub txt: Option<String>,
+    pub provider: ProviderConfig,
+    /// Publicar registro A.
+    #[serde(default = "default_true")]
+    pub ipv4: bool,
+    /// Publicar registro AAAA.
+    #[serde(default)]
+    pub ipv6: bool,
+    /// Etiquetas para rutear sus alertas, ej. "prod".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Forma en disco de `Entry`. Antes el token de DuckDNS iba suelto en la
+/// entrada y `provider` era opcional; al guardar queda en `provider`.
+#[derive(Deserialize)]
+struct EntryFile {
+    name: String,
+    #[serde(default)]
+    token: String,
+    activated: bool,
+    txt: Option<String>,
+    provider: Option<ProviderConfig>,
+    #[serde(default = "default_true")]
+    ipv4: bool,
+    #[serde(default)]
+    ipv6: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+impl From<EntryFile> for Entry {
+    fn from(file: EntryFile) -> Self {
+        let provider = match file.provider {
+            None => ProviderConfig::DuckDns { token: file.token },
+            Some(ProviderConfig::DuckDns { token }) if token.is_empty() => {
+                ProviderConfig::DuckDns { token: file.token }
+            }
+            Some(provider) => provider,
+        };
+        Self {
+            name: file.name,
+            activated: file.activated,
+            txt: file.txt,
+            provider,
+            ipv4: file.ipv4,
+            ipv6: file.ipv6,
+            tags: file.tags,
+        }
+    }
+}
+
+impl Entry {
+    /// Huella de lo que se publica (nombre, proveedor, TXT y familias).
+    /// Si cambia mientras el daemon está parado, se vuelve a publicar.
+    pub fn config_hash(&self) -> String {
+        let published = (&self.name, &self.provider, &self.txt, self.ipv4, self.ipv6);
+        let json = serde_json::to_vec(&published).unwrap_or_default();
+        let digest = ring::digest::digest(&ring::digest::SHA256, &json);
+        digest.as_ref()[..8].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Nombre completo a resolver. En DuckDNS `name` suele ser solo el
+    /// subdominio ("casa"), que vive bajo `duckdns.org`.
+    pub fn fqdn(&self) -> String {
+        match self.provider {
+            ProviderConfig::DuckDns { .. } if !self.name.ends_with(".duckdns.org") => {
+                format!("{}.duckdns.org", self.name)
+            }
+            _ => self.name.clone(),
+        }
+    }
 }
 
 impl fmt::Display for Entry {
@@ -16,6 +174,44 @@ impl fmt::Display for Entry {
             Some(v) => v,
             None => &String::new()            
         };
-        writeln!(f, "Name: {}, Activated:{}, txt: {} ", self.name, self.activated,txt )
+        writeln!(f, "Name: {}, Provider: {}, Activated:{}, IPv4: {}, IPv6: {}, txt: {} ", self.name, self.provider.name(), self.activated, self.ipv4, self.ipv6, txt )
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_legacy_token_moves_to_provider() {
+        let entries: Vec<Entry> = serde_json::from_str(
+            r#"[
+                {"name": "casa", "token": "t1", "activated": true, "txt": null},
+                {"name": "oficina", "token": "t2", "activated": true, "txt": null, "provider": {"type": "duckdns"}},
+                {"name": "www.example.com", "token": "", "activated": true, "txt": null,
+                 "provider": {"type": "dyndns2", "server": "https://dyn.example", "username": "u", "password": "p"}}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(entries[0].provider, ProviderConfig::DuckDns { token: "t1".into() });
+        assert_eq!(entries[1].provider, ProviderConfig::DuckDns { token: "t2".into() });
+        assert_eq!(entries[2].provider.name(), "dyndns2");
+
+        let saved = serde_json::to_value(&entries[0]).unwrap();
+        assert!(saved.get("token").is_none());
+        assert_eq!(saved["provider"]["token"], "t1");
+    }
+
+    #[test]
+    fn test_only_proxied_cloudflare_skips_dns_check() {
+        let cloudflare: ProviderConfig = serde_json::from_str(
+            r#"{"type": "cloudflare", "api_token": "x", "zone": "example.com", "proxied": true}"#,
+        )
+        .unwrap();
+        assert!(cloudflare.is_proxied());
+        let direct: ProviderConfig =
+            serde_json::from_str(r#"{"type": "cloudflare", "api_token": "x", "zone": "example.com"}"#).unwrap();
+        assert!(!direct.is_proxied());
+        assert!(!ProviderConfig::DuckDns { token: "t".into() }.is_proxied());
+    }
+}
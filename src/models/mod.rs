@@ -1 +1,4 @@
-pub mod entry;
\ No newline at end of file
+pub mod entry;
+pub mod log;
+pub mod settings;
+pub mod state;
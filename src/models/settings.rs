@@ -0,0 +1,492 @@
+use std::fmt;
+
+use chrono::{FixedOffset, Local};
+use serde::{Deserialize, Serialize};
+
+use std::time::Duration;
+
+use super::log::Level;
+
+/// Versión actual del formato de `settings.json`.
+pub const SETTINGS_VERSION: u32 = 3;
+
+/// Configuración del remitente de alertas por correo.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MailConfig {
+    pub smtp_server: String, // ejemplo: "smtp.gmail.com"
+    pub smtp_port: u16,      // 587 para STARTTLS
+    pub sender: String,      // correo origen
+    pub password: String,    // clave de aplicación (no tu password real)
+    pub recipient: String,   // correo destino
+}
+
+/// Tipo de evento que dispara una alerta.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    UpdateError,
+    DnsMismatch,
+    PublicIpError,
+    StopError,
+    IpDisagreement,
+    EntryDisabled,
+    ServiceStopping,
+}
+
+impl AlertKind {
+    /// Severidad con la que se rutea.
+    pub fn severity(self) -> Level {
+        match self {
+            AlertKind::EntryDisabled | AlertKind::StopError => Level::Error,
+            AlertKind::UpdateError | AlertKind::DnsMismatch | AlertKind::PublicIpError => Level::Warn,
+            AlertKind::IpDisagreement | AlertKind::ServiceStopping => Level::Info,
+        }
+    }
+}
+
+impl NotificationSettings {
+    /// Si la configuración pide avisar de `kind`.
+    pub fn allows(&self, kind: AlertKind) -> bool {
+        match kind {
+            AlertKind::UpdateError => self.on_update_error,
+            AlertKind::DnsMismatch => self.on_dns_mismatch,
+            AlertKind::PublicIpError => self.on_public_ip_error,
+            AlertKind::StopError => self.on_stop_error,
+            AlertKind::IpDisagreement => self.on_ip_disagreement,
+            AlertKind::EntryDisabled => self.on_entry_disabled,
+            AlertKind::ServiceStopping => self.on_service_stopping,
+        }
+    }
+}
+
+/// Qué eventos generan una notificación.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    pub on_update_error: bool,
+    pub on_dns_mismatch: bool,
+    pub on_public_ip_error: bool,
+    pub on_stop_error: bool,
+    /// Fuentes de IP pública que no coinciden entre sí.
+    pub on_ip_disagreement: bool,
+    /// Un dominio fue desactivado por un error permanente (token inválido).
+    pub on_entry_disabled: bool,
+    /// El servicio se detuvo de forma ordenada (señal o `stop`).
+    pub on_service_stopping: bool,
+    /// Canales además del correo de `mail`, que se llama "email".
+    pub channels: Vec<ChannelConfig>,
+    /// Reglas de ruteo. Sin reglas cada alerta va a todos los canales;
+    /// con reglas, a los de todas las que coincidan.
+    pub routes: Vec<RouteRule>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            on_update_error: true,
+            on_dns_mismatch: true,
+            on_public_ip_error: true,
+            on_stop_error: true,
+            on_ip_disagreement: false,
+            on_entry_disabled: true,
+            on_service_stopping: false,
+            channels: Vec::new(),
+            routes: Vec::new(),
+        }
+    }
+}
+
+/// Un canal de notificación con nombre, al que apuntan las rutas.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ChannelConfig {
+    Email {
+        name: String,
+        #[serde(flatten)]
+        mail: MailConfig,
+    },
+    /// POST con JSON a una URL (Slack, Discord o un receptor propio).
+    Webhook {
+        name: String,
+        url: String,
+        #[serde(default)]
+        format: WebhookFormat,
+    },
+    /// Mensaje de un bot de Telegram a un chat.
+    Telegram {
+        name: String,
+        bot_token: String,
+        chat_id: String,
+        /// Base alternativa de la API (pruebas o proxies).
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        api_base: Option<String>,
+    },
+}
+
+impl ChannelConfig {
+    pub fn name(&self) -> &str {
+        match self {
+            ChannelConfig::Email { name, .. }
+            | ChannelConfig::Webhook { name, .. }
+            | ChannelConfig::Telegram { name, .. } => name,
+        }
+    }
+}
+
+/// Sin credenciales: solo el tipo y el nombre.
+impl fmt::Debug for ChannelConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            ChannelConfig::Email { .. } => "email",
+            ChannelConfig::Webhook { .. } => "webhook",
+            ChannelConfig::Telegram { .. } => "telegram",
+        };
+        write!(f, "{}({})", kind, self.name())
+    }
+}
+
+/// Cuerpo que se envía a un webhook.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// La alerta completa: tipo, severidad, dominio, asunto y cuerpo.
+    #[default]
+    Json,
+    /// `{"text": ...}`, también sirve para Mattermost y Rocket.Chat.
+    Slack,
+    /// `{"content": ...}`.
+    Discord,
+}
+
+/// Qué alertas van a qué canales. Los criterios vacíos no filtran; una
+/// alerta coincide si cumple todos los demás.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RouteRule {
+    /// Tipos de alerta, ej. `entry_disabled`.
+    pub kinds: Vec<AlertKind>,
+    /// Severidad mínima.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_severity: Option<Level>,
+    /// Nombres de dominio.
+    pub domains: Vec<String>,
+    /// Etiquetas de dominio; alcanza con que el dominio tenga una.
+    pub tags: Vec<String>,
+    /// Nombres de los canales destino.
+    pub channels: Vec<String>,
+}
+
+/// Servicios que devuelven la IP de quien consulta vía DNS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsIpService {
+    /// `myip.opendns.com A @resolver1.opendns.com`
+    OpenDns,
+    /// `o-o.myaddr.l.google.com TXT @ns1.google.com`
+    Google,
+    /// `whoami.cloudflare CH TXT @1.1.1.1`
+    Cloudflare,
+}
+
+impl DnsIpService {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DnsIpService::OpenDns => "opendns",
+            DnsIpService::Google => "google",
+            DnsIpService::Cloudflare => "cloudflare",
+        }
+    }
+
+    pub fn default_timeout(&self) -> Duration {
+        match self {
+            DnsIpService::OpenDns => Duration::from_secs(2),
+            DnsIpService::Google => Duration::from_secs(3),
+            DnsIpService::Cloudflare => Duration::from_secs(2),
+        }
+    }
+}
+
+/// Una fuente de IP pública.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IpSourceConfig {
+    /// Consulta DNS a un servicio "cuál es mi IP".
+    Dns {
+        service: DnsIpService,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    /// Servicio HTTP que devuelve la IP en texto plano o en un campo JSON.
+    Http {
+        url: String,
+        #[serde(default)]
+        json_field: Option<String>,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    /// Dirección asignada a una interfaz local (VPS, PPPoE, routers).
+    /// Sirve tanto para IPv4 como para IPv6; con `ipv6` siempre lee la IPv6.
+    Interface {
+        name: String,
+        #[serde(default)]
+        ipv6: bool,
+    },
+    /// DuckDNS detecta la IP al actualizar sin `ip=`.
+    /// Ojo: actualiza `domain` como efecto secundario.
+    DuckDns { domain: String, token: String },
+}
+
+/// Cómo se decide la IP pública de una familia.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IpDetectionSettings {
+    pub sources: Vec<IpSourceConfig>,
+    /// Cuántas fuentes deben coincidir para aceptar una IP.
+    pub quorum: usize,
+}
+
+impl Default for IpDetectionSettings {
+    fn default() -> Self {
+        Self {
+            sources: vec![
+                IpSourceConfig::Dns {
+                    service: DnsIpService::OpenDns,
+                    timeout_ms: None,
+                },
+                IpSourceConfig::Dns {
+                    service: DnsIpService::Cloudflare,
+                    timeout_ms: None,
+                },
+                IpSourceConfig::Http {
+                    url: "https://api.ipify.org".into(),
+                    json_field: None,
+                    timeout_ms: None,
+                },
+            ],
+            quorum: 2,
+        }
+    }
+}
+
+impl IpDetectionSettings {
+    /// Fuentes por defecto para IPv6: las DNS se consultan por IPv6.
+    pub fn default_ipv6() -> Self {
+        Self {
+            sources: vec![
+                IpSourceConfig::Dns {
+                    service: DnsIpService::OpenDns,
+                    timeout_ms: None,
+                },
+                IpSourceConfig::Dns {
+                    service: DnsIpService::Cloudflare,
+                    timeout_ms: None,
+                },
+                IpSourceConfig::Http {
+                    url: "https://api6.ipify.org".into(),
+                    json_field: None,
+                    timeout_ms: None,
+                },
+            ],
+            quorum: 2,
+        }
+    }
+}
+
+/// Un resolver usado para verificar la propagación.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolverConfig {
+    /// "ns1.duckdns.org", "1.1.1.1", "192.0.2.53:5353" o "system"
+    /// (el primer `nameserver` de /etc/resolv.conf).
+    pub server: String,
+    /// Servidor autoritativo: se consulta sin recursión y su respuesta
+    /// indica si la actualización se aplicó.
+    #[serde(default)]
+    pub authoritative: bool,
+    /// Solo se consulta para nombres bajo esta zona, ej. "duckdns.org".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
+}
+
+/// Cómo se verifica que el DNS publicado coincide con la IP.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DnsCheckSettings {
+    pub resolvers: Vec<ResolverConfig>,
+    pub timeout_ms: u64,
+}
+
+impl Default for DnsCheckSettings {
+    fn default() -> Self {
+        let duck = |ns: &str| ResolverConfig {
+            server: ns.into(),
+            authoritative: true,
+            zone: Some("duckdns.org".into()),
+        };
+        let cache = |server: &str| ResolverConfig {
+            server: server.into(),
+            authoritative: false,
+            zone: None,
+        };
+        Self {
+            resolvers: vec![
+                duck("ns1.duckdns.org"),
+                duck("ns2.duckdns.org"),
+                duck("ns3.duckdns.org"),
+                cache("system"),
+                cache("1.1.1.1"),
+                cache("8.8.8.8"),
+            ],
+            timeout_ms: 3000,
+        }
+    }
+}
+
+/// Log de eventos (`logs/events.jsonl`) y su rotación.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    /// Se rota al superar este tamaño, en KB.
+    pub max_size_kb: u64,
+    /// Se rota cuando el primer evento del archivo tiene más de estas horas.
+    pub max_age_hours: u64,
+    /// Archivos rotados (comprimidos con gzip) que se conservan.
+    pub retention: usize,
+    /// Dónde se escriben los eventos.
+    pub sinks: Vec<SinkConfig>,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            max_size_kb: 1024,
+            max_age_hours: 7 * 24,
+            retention: 8,
+            sinks: vec![SinkConfig::File],
+        }
+    }
+}
+
+/// Un destino del log de eventos.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkConfig {
+    /// `logs/events.jsonl`, el que lee `view-log`.
+    File,
+    /// journald con su protocolo nativo; los campos DOMAIN= e IP= quedan
+    /// disponibles en `journalctl`.
+    Journald {
+        #[serde(default = "default_journal_socket")]
+        socket: String,
+    },
+    /// Syslog RFC 5424.
+    Syslog {
+        transport: SyslogTransport,
+        /// "host:puerto" para udp/tcp o la ruta del socket, ej. "/dev/log".
+        address: String,
+        /// Facility syslog; 3 es "daemon".
+        #[serde(default = "default_syslog_facility")]
+        facility: u8,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogTransport {
+    Udp,
+    Tcp,
+    Unix,
+}
+
+fn default_journal_socket() -> String {
+    "/run/systemd/journal/socket".into()
+}
+
+fn default_syslog_facility() -> u8 {
+    3
+}
+
+/// Un archivo sin `version` es anterior al versionado y se migra.
+fn unversioned() -> u32 {
+    1
+}
+
+/// Ajustes de ejecución del servicio, guardados junto a `domainhdlr.json`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    #[serde(default = "unversioned")]
+    pub version: u32,
+    /// Sin `mail` no se envían correos.
+    pub mail: Option<MailConfig>,
+    /// Intervalo de sondeo entre ciclos, en milisegundos. Los cambios de
+    /// red detectados por netlink disparan un ciclo antes.
+    pub poll_interval_ms: u64,
+    /// Primer reintento tras un fallo; se duplica en cada fallo seguido.
+    pub retry_base_ms: u64,
+    /// Tope del backoff entre reintentos.
+    pub retry_max_ms: u64,
+    /// Dominios (o lotes de DuckDNS) que se actualizan a la vez.
+    pub max_concurrent_updates: usize,
+    /// Tope para cada llamada al proveedor, en milisegundos.
+    pub request_timeout_ms: u64,
+    /// Usuario al que pasa `start --detached` si arranca como root. Dominios,
+    /// estado y logs van a `/var/lib/domainhdlr` y el socket a
+    /// `/run/domainhdlr`, de ese usuario; el archivo de settings queda
+    /// donde está y tiene que poder leerlo.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_as: Option<String>,
+    /// Offset horario usado en los mensajes, ej. "-03:00".
+    pub timezone: String,
+    pub notifications: NotificationSettings,
+    /// Detección de la IPv4 pública.
+    pub ip_detection: IpDetectionSettings,
+    /// Detección de la IPv6 pública; solo se usa si algún dominio tiene `ipv6`.
+    pub ipv6_detection: IpDetectionSettings,
+    pub dns_check: DnsCheckSettings,
+    pub log: LogSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            mail: None,
+            poll_interval_ms: 5 * 60 * 1000,
+            retry_base_ms: 5 * 1000,
+            retry_max_ms: 10 * 60 * 1000,
+            max_concurrent_updates: 4,
+            request_timeout_ms: 15 * 1000,
+            run_as: None,
+            timezone: "-03:00".into(),
+            notifications: NotificationSettings::default(),
+            ip_detection: IpDetectionSettings::default(),
+            ipv6_detection: IpDetectionSettings::default_ipv6(),
+            dns_check: DnsCheckSettings::default(),
+            log: LogSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Tope para cada pedido a un proveedor.
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_millis(self.request_timeout_ms)
+    }
+
+    /// Offset configurado; UTC si `timezone` no es válido.
+    pub fn tz_offset(&self) -> FixedOffset {
+        self.timezone
+            .parse::<FixedOffset>()
+            .unwrap_or_else(|_| FixedOffset::east_opt(0).unwrap())
+    }
+
+    /// Hora actual formateada en la zona configurada.
+    pub fn now_str(&self) -> String {
+        Local::now()
+            .with_timezone(&self.tz_offset())
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    }
+}
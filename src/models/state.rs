@@ -0,0 +1,68 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Estado del daemon que sobrevive a reinicios, en `state.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaemonState {
+    /// Última IP pública detectada, como la muestra `Addresses`.
+    #[serde(default)]
+    pub last_ip: String,
+    #[serde(default)]
+    pub domains: BTreeMap<String, DomainState>,
+}
+
+/// Historial de actualizaciones de un dominio.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomainState {
+    /// Direcciones que el proveedor aceptó; el DNS las confirmó salvo que
+    /// haya `failures`.
+    #[serde(default)]
+    pub last_pushed: Option<String>,
+    /// `Entry::config_hash` de la configuración con la que se publicó.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    #[serde(default)]
+    pub last_success: Option<String>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub last_error_at: Option<String>,
+    /// Intentos fallidos seguidos desde el último éxito.
+    #[serde(default)]
+    pub failures: u32,
+}
+
+impl DomainState {
+    pub fn record_success(&mut self, pushed: String, config_hash: String, now: String) {
+        self.last_pushed = Some(pushed);
+        self.config_hash = Some(config_hash);
+        self.last_success = Some(now);
+        self.failures = 0;
+    }
+
+    /// El proveedor aceptó `pushed` pero el DNS no lo confirmó: no se
+    /// vuelve a empujar, solo se repite la verificación.
+    pub fn record_unverified(&mut self, pushed: String, config_hash: String, error: String, now: String) {
+        self.last_pushed = Some(pushed);
+        self.config_hash = Some(config_hash);
+        self.last_error = Some(error);
+        self.last_error_at = Some(now);
+        self.failures += 1;
+    }
+
+    /// Si lo último publicado es `pushed` con la configuración `config_hash`.
+    pub fn is_current(&self, pushed: &str, config_hash: &str) -> bool {
+        self.last_pushed.as_deref() == Some(pushed) && self.config_hash.as_deref() == Some(config_hash)
+    }
+
+    /// Tras un fallo no se sabe qué quedó publicado: se olvida lo último
+    /// empujado para que el próximo ciclo vuelva a intentarlo.
+    pub fn record_error(&mut self, error: String, now: String) {
+        self.last_pushed = None;
+        self.config_hash = None;
+        self.last_error = Some(error);
+        self.last_error_at = Some(now);
+        self.failures += 1;
+    }
+}
@@ -0,0 +1,266 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::models::entry::Entry;
+use crate::process::http_client::http_client;
+use crate::process::provider::{Addresses, DnsProvider, ProviderResult, UpdateResponse};
+
+pub const CLOUDFLARE_API: &str = "https://api.cloudflare.com/client/v4";
+
+/// Sobre común de todas las respuestas de la API v4.
+#[derive(Debug, Deserialize)]
+struct ApiEnvelope {
+    success: bool,
+    #[serde(default)]
+    errors: Vec<ApiMessage>,
+    #[serde(default)]
+    result: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiMessage {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdOnly {
+    id: String,
+}
+
+/// Backend Cloudflare (API v4) con un token limitado a `Zone.DNS:Edit`.
+/// `Entry::name` es el nombre completo del registro, ej. `home.example.com`.
+pub struct Cloudflare {
+    client: Client,
+    api_base: String,
+    api_token: String,
+    zone: String,
+    proxied: bool,
+    ttl: u32,
+    timeout: Duration,
+}
+
+impl Cloudflare {
+    pub fn new(
+        api_token: &str,
+        zone: &str,
+        proxied: bool,
+        ttl: u32,
+        api_base: Option<&str>,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            client: http_client().clone(),
+            api_base: api_base.unwrap_or(CLOUDFLARE_API).trim_end_matches('/').to_string(),
+            api_token: api_token.to_string(),
+            zone: zone.to_string(),
+            proxied,
+            ttl,
+            timeout,
+        }
+    }
+
+    fn auth(&self, req: RequestBuilder) -> RequestBuilder {
+        req.bearer_auth(&self.api_token)
+    }
+
+    /// Envía la petición y devuelve el `result` si la API respondió `success`.
+    async fn call(&self, req: RequestBuilder) -> ProviderResult<Value> {
+        let body = self.auth(req).timeout(self.timeout).send().await?.text().await?;
+        let env: ApiEnvelope = serde_json::from_str(&body)
+            .map_err(|e| format!("Unexpected Cloudflare response: {} | {}", e, body))?;
+        if !env.success {
+            return Err(describe_errors(&env.errors).into());
+        }
+        Ok(env.result)
+    }
+
+    async fn zone_id(&self) -> ProviderResult<String> {
+        let url = format!("{}/zones", self.api_base);
+        let result = self
+            .call(self.client.get(&url).query(&[("name", self.zone.as_str())]))
+            .await?;
+        let zones: Vec<IdOnly> = serde_json::from_value(result)?;
+        zones
+            .into_iter()
+            .next()
+            .map(|z| z.id)
+            .ok_or_else(|| format!("Zone '{}' not found in Cloudflare", self.zone).into())
+    }
+
+    async fn record_id(&self, zone_id: &str, name: &str, kind: &str) -> ProviderResult<Option<String>> {
+        let url = format!("{}/zones/{}/dns_records", self.api_base, zone_id);
+        let result = self
+            .call(self.client.get(&url).query(&[("type", kind), ("name", name)]))
+            .await?;
+        let records: Vec<IdOnly> = serde_json::from_value(result)?;
+        Ok(records.into_iter().next().map(|r| r.id))
+    }
+
+    /// Actualiza el registro con PATCH, o lo crea si todavía no existe.
+    async fn upsert(&self, name: &str, kind: &str, body: Value) -> ProviderResult<UpdateResponse> {
+        let zone_id = self.zone_id().await?;
+        let base = format!("{}/zones/{}/dns_records", self.api_base, zone_id);
+        let req = match self.record_id(&zone_id, name, kind).await? {
+            Some(id) => self.client.patch(format!("{}/{}", base, id)),
+            None => self.client.post(&base),
+        };
+        self.call(req.json(&body)).await?;
+        Ok(UpdateResponse::ok(format!("{} {} -> {}", kind, name, body["content"])))
+    }
+}
+
+fn describe_errors(errors: &[ApiMessage]) -> String {
+    if errors.is_empty() {
+        return "Cloudflare API returned success=false".to_string();
+    }
+    errors
+        .iter()
+        .map(|e| format!("[{}] {}", e.code, e.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[async_trait]
+impl DnsProvider for Cloudflare {
+    fn name(&self) -> &'static str {
+        "cloudflare"
+    }
+
+    async fn update(&self, entry: &Entry, addrs: &Addresses) -> ProviderResult<UpdateResponse> {
+        let records = [
+            ("A", addrs.ipv4.map(IpAddr::V4)),
+            ("AAAA", addrs.ipv6.map(IpAddr::V6)),
+        ];
+        let mut details = Vec::new();
+        for (kind, ip) in records {
+            let Some(ip) = ip else { continue };
+            let body = json!({
+                "type": kind,
+                "name": entry.name,
+                "content": ip.to_string(),
+                "proxied": self.proxied,
+                "ttl": self.ttl,
+            });
+            details.push(self.upsert(&entry.name, kind, body).await?.detail);
+        }
+        if let Some(txt) = &entry.txt {
+            details.push(self.set_txt(entry, txt).await?.detail);
+        }
+        Ok(UpdateResponse::ok(details.join(" | ")))
+    }
+
+    async fn set_txt(&self, entry: &Entry, value: &str) -> ProviderResult<UpdateResponse> {
+        let body = json!({
+            "type": "TXT",
+            "name": entry.name,
+            "content": value,
+            "ttl": self.ttl,
+        });
+        self.upsert(&entry.name, "TXT", body).await
+    }
+
+    async fn clear_txt(&self, entry: &Entry) -> ProviderResult<UpdateResponse> {
+        let zone_id = self.zone_id().await?;
+        let Some(id) = self.record_id(&zone_id, &entry.name, "TXT").await? else {
+            return Ok(UpdateResponse::ok(format!("No TXT record for {}", entry.name)));
+        };
+        let url = format!("{}/zones/{}/dns_records/{}", self.api_base, zone_id, id);
+        self.call(self.client.delete(&url)).await?;
+        Ok(UpdateResponse::ok(format!("TXT {} deleted", entry.name)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::entry::ProviderConfig;
+    use mockito::Matcher;
+
+    fn entry() -> Entry {
+        Entry {
+            name: "home.example.com".into(),
+            activated: true,
+            txt: None,
+            ipv4: true,
+            ipv6: false,
+            tags: Vec::new(),
+            provider: ProviderConfig::Cloudflare {
+                api_token: "secret".into(),
+                zone: "example.com".into(),
+                proxied: true,
+                ttl: 120,
+                api_base: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_patches_existing_record() {
+        let mut server = mockito::Server::new_async().await;
+        let zones = server
+            .mock("GET", "/zones")
+            .match_query(Matcher::UrlEncoded("name".into(), "example.com".into()))
+            .match_header("authorization", "Bearer secret")
+            .with_body(r#"{"success":true,"errors":[],"result":[{"id":"z1"}]}"#)
+            .create_async()
+            .await;
+        let records = server
+            .mock("GET", "/zones/z1/dns_records")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("type".into(), "A".into()),
+                Matcher::UrlEncoded("name".into(), "home.example.com".into()),
+            ]))
+            .with_body(r#"{"success":true,"errors":[],"result":[{"id":"r1"}]}"#)
+            .create_async()
+            .await;
+        let patch = server
+            .mock("PATCH", "/zones/z1/dns_records/r1")
+            .match_body(Matcher::PartialJson(json!({
+                "content": "203.0.113.7",
+                "proxied": true,
+                "ttl": 120,
+            })))
+            .with_body(r#"{"success":true,"errors":[],"result":{"id":"r1"}}"#)
+            .create_async()
+            .await;
+
+        let cf = Cloudflare::new("secret", "example.com", true, 120, Some(&server.url()), Duration::from_secs(5));
+        let addrs = Addresses {
+            ipv4: Some("203.0.113.7".parse().unwrap()),
+            ipv6: None,
+        };
+        let res = cf.update(&entry(), &addrs).await.unwrap();
+
+        assert!(res.success);
+        zones.assert_async().await;
+        records.assert_async().await;
+        patch.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_api_errors_are_reported() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/zones")
+            .match_query(Matcher::Any)
+            .with_status(403)
+            .with_body(r#"{"success":false,"errors":[{"code":10000,"message":"Authentication error"}],"result":null}"#)
+            .create_async()
+            .await;
+
+        let cf = Cloudflare::new("bad", "example.com", false, 1, Some(&server.url()), Duration::from_secs(5));
+        let addrs = Addresses {
+            ipv4: Some("203.0.113.7".parse().unwrap()),
+            ipv6: None,
+        };
+        let err = cf.update(&entry(), &addrs).await.unwrap_err();
+
+        assert!(err.to_string().contains("Authentication error"));
+    }
+}
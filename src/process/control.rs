@@ -0,0 +1,266 @@
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+
+use crate::models::state::DaemonState;
+
+use super::rutas::control_socket;
+
+/// Cuánto espera la CLI una respuesta del daemon.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Pedido de la CLI al daemon: una línea JSON, ej. `{"command":"status"}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    Status,
+    Stop,
+    Restart,
+    Reload,
+}
+
+/// Respuesta del daemon: una línea JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<DaemonStatus>,
+}
+
+impl ControlResponse {
+    pub fn ok(message: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            message: message.into(),
+            status: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            message: message.into(),
+            status: None,
+        }
+    }
+}
+
+/// Estado que el daemon publica para `status`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub started_at: String,
+    /// Inicio en ms Unix; distingue la instancia nueva tras `restart`,
+    /// que conserva el PID.
+    pub started_ms: u64,
+    pub uptime_secs: u64,
+    pub public_ip: String,
+    pub last_cycle: Option<String>,
+    pub failures: u32,
+    pub domains: usize,
+    pub active_domains: usize,
+    /// Lo mismo que `state.json`, tal como lo tiene el loop.
+    #[serde(default)]
+    pub state: DaemonState,
+}
+
+pub type SharedStatus = Arc<Mutex<DaemonStatus>>;
+
+/// Orden que el servidor de control le pasa al loop.
+#[derive(Debug)]
+pub enum ControlCommand {
+    Stop,
+    Restart,
+    /// Releer settings y dominios; el resultado vuelve por el canal.
+    Reload(Option<oneshot::Sender<Result<(), String>>>),
+}
+
+pub fn new_status(started_at: String) -> SharedStatus {
+    Arc::new(Mutex::new(DaemonStatus {
+        pid: std::process::id(),
+        started_at,
+        started_ms: unix_now_ms(),
+        ..DaemonStatus::default()
+    }))
+}
+
+fn unix_now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Canal de órdenes para el loop; lo alimentan el socket y las señales.
+pub fn command_channel() -> (UnboundedSender<ControlCommand>, UnboundedReceiver<ControlCommand>) {
+    unbounded_channel()
+}
+
+/// Abre el socket de control y atiende pedidos en segundo plano,
+/// reenviando las órdenes por `tx`.
+pub fn serve(status: SharedStatus, tx: UnboundedSender<ControlCommand>) -> io::Result<()> {
+    serve_at(&control_socket(), status, tx)
+}
+
+fn serve_at(path: &Path, status: SharedStatus, tx: UnboundedSender<ControlCommand>) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if path.exists() {
+        // Si alguien contesta, hay otro daemon; si no, es un socket viejo
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("Another instance is listening on {}", path.display()),
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let (status, tx) = (status.clone(), tx.clone());
+            tokio::spawn(async move {
+                if let Err(e) = handle_client(stream, status, tx).await {
+                    tracing::warn!("Control connection failed: {}", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+async fn handle_client(
+    stream: UnixStream,
+    status: SharedStatus,
+    tx: UnboundedSender<ControlCommand>,
+) -> io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut line = String::new();
+    BufReader::new(read).read_line(&mut line).await?;
+
+    let response = match serde_json::from_str::<ControlRequest>(&line) {
+        Ok(request) => dispatch(request, &status, &tx).await,
+        Err(e) => ControlResponse::error(format!("Invalid request: {}", e)),
+    };
+    let mut out = serde_json::to_vec(&response)?;
+    out.push(b'\n');
+    write.write_all(&out).await
+}
+
+async fn dispatch(
+    request: ControlRequest,
+    status: &SharedStatus,
+    tx: &UnboundedSender<ControlCommand>,
+) -> ControlResponse {
+    let gone = || ControlResponse::error("Daemon loop is not running");
+    match request {
+        ControlRequest::Status => {
+            let mut current = status.lock().unwrap().clone();
+            current.uptime_secs = unix_now_ms().saturating_sub(current.started_ms) / 1000;
+            ControlResponse {
+                status: Some(current),
+                ..ControlResponse::ok("running")
+            }
+        }
+        ControlRequest::Stop => match tx.send(ControlCommand::Stop) {
+            Ok(_) => ControlResponse::ok("stopping"),
+            Err(_) => gone(),
+        },
+        ControlRequest::Restart => match tx.send(ControlCommand::Restart) {
+            Ok(_) => ControlResponse::ok("restarting"),
+            Err(_) => gone(),
+        },
+        ControlRequest::Reload => {
+            let (reply, result) = oneshot::channel();
+            if tx.send(ControlCommand::Reload(Some(reply))).is_err() {
+                return gone();
+            }
+            match result.await {
+                Ok(Ok(())) => ControlResponse::ok("reloaded"),
+                Ok(Err(e)) => ControlResponse::error(format!("Reload failed: {}", e)),
+                Err(_) => gone(),
+            }
+        }
+    }
+}
+
+/// Borra el socket al terminar.
+pub fn remove_socket() {
+    let _ = std::fs::remove_file(control_socket());
+}
+
+/// Envía un pedido al daemon. `NotFound`/`ConnectionRefused` significan
+/// que no hay daemon corriendo.
+pub async fn send_request(request: ControlRequest) -> io::Result<ControlResponse> {
+    send_request_to(&control_socket(), request).await
+}
+
+async fn send_request_to(path: &Path, request: ControlRequest) -> io::Result<ControlResponse> {
+    let exchange = async {
+        let stream = UnixStream::connect(path).await?;
+        let (read, mut write) = stream.into_split();
+        let mut out = serde_json::to_vec(&request)?;
+        out.push(b'\n');
+        write.write_all(&out).await?;
+
+        let mut line = String::new();
+        BufReader::new(read).read_line(&mut line).await?;
+        serde_json::from_str(&line).map_err(io::Error::other)
+    };
+    timeout(REQUEST_TIMEOUT, exchange)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "Daemon did not answer"))?
+}
+
+/// El error indica que no hay nadie escuchando en el socket.
+pub fn is_not_running(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_protocol_is_tagged_json() {
+        assert_eq!(serde_json::to_string(&ControlRequest::Reload).unwrap(), r#"{"command":"reload"}"#);
+        let req: ControlRequest = serde_json::from_str(r#"{"command":"status"}"#).unwrap();
+        assert_eq!(req, ControlRequest::Status);
+        assert!(serde_json::from_str::<ControlRequest>(r#"{"command":"kill"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_over_socket() {
+        let path = std::env::temp_dir().join(format!("domainhdlr-test-{}.sock", std::process::id()));
+        let (tx, mut commands) = command_channel();
+        serve_at(&path, new_status("now".into()), tx.clone()).unwrap();
+
+        let status = send_request_to(&path, ControlRequest::Status).await.unwrap();
+        assert!(status.ok);
+        assert_eq!(status.status.unwrap().pid, std::process::id());
+
+        let stop = send_request_to(&path, ControlRequest::Stop).await.unwrap();
+        assert!(stop.ok);
+        assert!(matches!(commands.recv().await, Some(ControlCommand::Stop)));
+
+        // Una segunda instancia no puede tomar el mismo socket
+        assert!(serve_at(&path, new_status("now".into()), tx).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,261 @@
+use std::ffi::CString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{PermissionsExt, lchown};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::models::settings::Settings;
+
+use super::rutas::{config_dir, daemon_err_file, daemon_out_file, pid_file, runtime_dir, settings_file};
+
+/// Cuánto espera el proceso que lanzó `start --detached` a que el daemon
+/// confirme que arrancó.
+const READY_TIMEOUT_MS: i32 = 30_000;
+
+/// Extremo de escritura del pipe de arranque, solo en el daemon.
+static READY_PIPE: Mutex<Option<File>> = Mutex::new(None);
+
+/// De qué lado del fork quedó el proceso.
+pub enum Forked {
+    /// El proceso original; lee del pipe si el daemon arrancó.
+    Parent(File),
+    /// El daemon, ya separado de la terminal.
+    Daemon,
+}
+
+/// Usuario de `run_as`, resuelto antes del fork.
+struct RunAs {
+    name: CString,
+    uid: libc::uid_t,
+    gid: libc::gid_t,
+    home: CString,
+}
+
+/// Pasa a segundo plano: doble fork, `setsid`, cambio de usuario opcional
+/// y stdio a archivos. Debe llamarse antes de crear el runtime
+/// de tokio: con hilos corriendo `fork` no es seguro.
+pub fn daemonize(settings: &Settings) -> io::Result<Forked> {
+    // Los errores de `run_as` se informan acá, antes de separarse
+    let run_as = settings.run_as.as_deref().map(prepare_run_as).transpose()?;
+    let (read_end, write_end) = pipe()?;
+
+    match unsafe { libc::fork() } {
+        -1 => return Err(io::Error::last_os_error()),
+        0 => {}
+        child => {
+            drop(write_end);
+            // El hijo intermedio sale enseguida
+            unsafe { libc::waitpid(child, std::ptr::null_mut(), 0) };
+            return Ok(Forked::Parent(read_end));
+        }
+    }
+    drop(read_end);
+
+    // Nueva sesión, sin terminal de control
+    if unsafe { libc::setsid() } == -1 {
+        fail_startup(write_end, &io::Error::last_os_error());
+    }
+    match unsafe { libc::fork() } {
+        -1 => fail_startup(write_end, &io::Error::last_os_error()),
+        0 => {}
+        // El líder de sesión sale para que el daemon nunca vuelva a tomar una terminal
+        _ => unsafe { libc::_exit(0) },
+    }
+
+    if let Err(e) = setup_daemon(run_as.as_ref()) {
+        fail_startup(write_end, &e);
+    }
+    *READY_PIPE.lock().unwrap() = Some(write_end);
+    Ok(Forked::Daemon)
+}
+
+fn setup_daemon(run_as: Option<&RunAs>) -> io::Result<()> {
+    if let Some(user) = run_as {
+        drop_privileges(user)?;
+    }
+    unsafe { libc::umask(0o027) };
+    std::env::set_current_dir("/")?;
+
+    let dir = config_dir();
+    fs::create_dir_all(&dir).map_err(|e| with_path(&dir, e))?;
+    let null = File::open("/dev/null")?;
+    let append = |path: PathBuf| {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| with_path(&path, e))
+    };
+    let (out, err) = (append(daemon_out_file())?, append(daemon_err_file())?);
+    redirect(null.as_raw_fd(), libc::STDIN_FILENO)?;
+    redirect(out.as_raw_fd(), libc::STDOUT_FILENO)?;
+    redirect(err.as_raw_fd(), libc::STDERR_FILENO)
+}
+
+/// Resuelve el usuario y le prepara los directorios de sistema (ver
+/// `use_system_dirs`). Solo se cambia el dueño de los directorios, no
+/// de lo que ya tengan adentro.
+fn prepare_run_as(name: &str) -> io::Result<RunAs> {
+    if unsafe { libc::geteuid() } != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("run_as '{}' requires starting the service as root", name),
+        ));
+    }
+    let cname = CString::new(name).map_err(io::Error::other)?;
+    let pw = unsafe { libc::getpwnam(cname.as_ptr()) };
+    if pw.is_null() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("Unknown user '{}'", name)));
+    }
+    let user = unsafe {
+        let pw = &*pw;
+        RunAs {
+            name: cname,
+            uid: pw.pw_uid,
+            gid: pw.pw_gid,
+            home: std::ffi::CStr::from_ptr(pw.pw_dir).to_owned(),
+        }
+    };
+
+    for dir in [config_dir(), runtime_dir()] {
+        fs::create_dir_all(&dir).map_err(|e| with_path(&dir, e))?;
+        // Un symlink acá le daría al usuario otro directorio del sistema
+        if !fs::symlink_metadata(&dir)?.is_dir() {
+            return Err(io::Error::other(format!("{} is not a directory", dir.display())));
+        }
+        lchown(&dir, Some(user.uid), Some(user.gid)).map_err(|e| with_path(&dir, e))?;
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o750))?;
+    }
+    Ok(user)
+}
+
+/// Cambia al usuario de `run_as` (grupos incluidos) y comprueba que
+/// pueda usar sus directorios y leer los settings.
+fn drop_privileges(user: &RunAs) -> io::Result<()> {
+    // Orden: grupos, gid y por último uid (después ya no se puede)
+    let ok = unsafe {
+        libc::initgroups(user.name.as_ptr(), user.gid as _) == 0
+            && libc::setgid(user.gid) == 0
+            && libc::setuid(user.uid) == 0
+    };
+    if !ok {
+        return Err(io::Error::last_os_error());
+    }
+    if unsafe { libc::setuid(0) } == 0 && user.uid != 0 {
+        return Err(io::Error::other("Privileges were not dropped"));
+    }
+    let name = user.name.to_string_lossy();
+    for (path, mode) in [
+        (config_dir(), libc::W_OK | libc::X_OK),
+        (runtime_dir(), libc::W_OK | libc::X_OK),
+        (settings_file(), libc::R_OK),
+    ] {
+        let cpath = CString::new(path.as_os_str().as_bytes()).map_err(io::Error::other)?;
+        if unsafe { libc::access(cpath.as_ptr(), mode) } != 0 {
+            let e = io::Error::last_os_error();
+            return Err(io::Error::new(
+                e.kind(),
+                format!("User '{}' cannot access {}: {}", name, path.display(), e),
+            ));
+        }
+    }
+    unsafe {
+        std::env::set_var("HOME", user.home.to_string_lossy().as_ref());
+        std::env::set_var("USER", name.as_ref());
+    }
+    Ok(())
+}
+
+fn with_path(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+}
+
+fn pipe() -> io::Result<(File, File)> {
+    let mut fds = [0 as RawFd; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) })
+}
+
+fn redirect(from: RawFd, to: RawFd) -> io::Result<()> {
+    if unsafe { libc::dup2(from, to) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn fail_startup(mut pipe: File, e: &io::Error) -> ! {
+    let _ = writeln!(pipe, "ERR 1 {}", e);
+    unsafe { libc::_exit(1) }
+}
+
+/// Informa al proceso que lanzó el daemon si el arranque salió bien.
+/// Sin pipe (arranque en primer plano) no hace nada.
+pub fn notify_ready(result: Result<(), (i32, String)>) {
+    let Some(mut pipe) = READY_PIPE.lock().unwrap().take() else {
+        return;
+    };
+    let _ = match result {
+        Ok(()) => writeln!(pipe, "OK {}", std::process::id()),
+        Err((code, msg)) => writeln!(pipe, "ERR {} {}", code, msg),
+    };
+}
+
+/// Espera la confirmación del daemon y devuelve el código de salida para
+/// el proceso que lo lanzó.
+pub fn wait_ready(mut pipe: File) -> i32 {
+    let mut poll = libc::pollfd {
+        fd: pipe.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    if unsafe { libc::poll(&mut poll, 1, READY_TIMEOUT_MS) } <= 0 {
+        eprintln!("Daemon did not report readiness in {} s", READY_TIMEOUT_MS / 1000);
+        return 1;
+    }
+
+    let mut msg = String::new();
+    let _ = pipe.read_to_string(&mut msg);
+    let msg = msg.trim();
+    if let Some(pid) = msg.strip_prefix("OK ") {
+        println!("Service started in background (pid {}).", pid);
+        return 0;
+    }
+    match msg.strip_prefix("ERR ").and_then(|rest| rest.split_once(' ')) {
+        Some((code, err)) => {
+            eprintln!("Error starting service: {}", err);
+            code.parse().unwrap_or(1)
+        }
+        None => {
+            eprintln!(
+                "Daemon exited during startup, see {}",
+                daemon_err_file().display()
+            );
+            1
+        }
+    }
+}
+
+/// Escribe el PID file; se llama con el lock de instancia ya tomado.
+pub fn write_pid_file() -> io::Result<()> {
+    let path = pid_file();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, format!("{}\n", std::process::id()))
+}
+
+/// Borra el PID file si es de este proceso.
+pub fn remove_pid_file() {
+    let path = pid_file();
+    let ours = fs::read_to_string(&path)
+        .map(|pid| pid.trim() == std::process::id().to_string())
+        .unwrap_or(false);
+    if ours {
+        let _ = fs::remove_file(path);
+    }
+}
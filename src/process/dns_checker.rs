@@ -1,24 +1,285 @@
-use std::process::Command;
-use std::net::Ipv4Addr;
-
-/// Ejecuta `nslookup domain` y devuelve la IP encontrada (si existe)
-pub fn check_dns_ip(domain: &str) -> Option<String> {
-    let output = Command::new("nslookup")
-        .arg(domain)
-        .output()
-        .ok()?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    // Buscar algo tipo: "Address: 203.0.113.5"
-    for line in stdout.lines() {
-        if line.trim_start().starts_with("Address:") {
-            if let Some(ip_str) = line.split_whitespace().nth(1) {
-                // Validar formato IP
-                if ip_str.parse::<Ipv4Addr>().is_ok() {
-                    return Some(ip_str.to_string());
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use futures::future::join_all;
+use hickory_proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::{Name, RData, RecordType};
+use tracing::instrument;
+
+use crate::models::settings::{DnsCheckSettings, ResolverConfig};
+
+use super::dns_wire::{exchange, resolve_server};
+
+/// Una dirección publicada, con el TTL que informó el resolver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Published {
+    pub ip: IpAddr,
+    pub ttl: u32,
+}
+
+/// Lo que contestó un resolver.
+#[derive(Debug, Clone)]
+pub struct ResolverAnswer {
+    pub resolver: String,
+    pub authoritative: bool,
+    pub result: Result<Vec<Published>, String>,
+}
+
+/// Estado de propagación de una IP esperada.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation {
+    /// Todos los resolvers que contestaron tienen la IP.
+    Synced,
+    /// La actualización se aplicó (o, sin autoritativos, no se puede
+    /// descartar) pero hay caches con el valor viejo; expiran en `ttl`
+    /// segundos como mucho.
+    CachesStale { ttl: u32 },
+    /// Los autoritativos no tienen la IP: la actualización no se aplicó.
+    NotApplied,
+    /// Ningún resolver contestó.
+    Unknown,
+}
+
+/// Respuestas de todos los resolvers para un nombre.
+#[derive(Debug, Clone)]
+pub struct DnsCheck {
+    pub name: String,
+    pub answers: Vec<ResolverAnswer>,
+}
+
+impl DnsCheck {
+    /// Clasifica cómo se ve `expected` desde los resolvers. Solo un
+    /// autoritativo puede decir que no se aplicó: sin ellos, que ningún
+    /// cache la tenga todavía es esperable justo después de actualizar.
+    pub fn propagation(&self, expected: IpAddr) -> Propagation {
+        let has = |recs: &[Published]| recs.iter().any(|p| p.ip == expected);
+        let ok: Vec<(&ResolverAnswer, &[Published])> = self
+            .answers
+            .iter()
+            .filter_map(|a| a.result.as_deref().ok().map(|r| (a, r)))
+            .collect();
+        if ok.is_empty() {
+            return Propagation::Unknown;
+        }
+
+        let mut auth = ok.iter().filter(|(a, _)| a.authoritative).peekable();
+        if auth.peek().is_some() && !auth.all(|(_, r)| has(r)) {
+            return Propagation::NotApplied;
+        }
+
+        if ok.iter().all(|(_, r)| has(r)) {
+            return Propagation::Synced;
+        }
+        let ttl = ok
+            .iter()
+            .filter(|(_, r)| !has(r))
+            .flat_map(|(_, r)| r.iter())
+            .filter(|p| p.ip.is_ipv6() == expected.is_ipv6())
+            .map(|p| p.ttl)
+            .max()
+            .unwrap_or(0);
+        Propagation::CachesStale { ttl }
+    }
+}
+
+impl fmt::Display for DnsCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self
+            .answers
+            .iter()
+            .map(|a| {
+                let label = if a.authoritative {
+                    format!("{}(auth)", a.resolver)
+                } else {
+                    a.resolver.clone()
+                };
+                match &a.result {
+                    Ok(recs) => {
+                        let recs: Vec<String> =
+                            recs.iter().map(|p| format!("{} ttl={}", p.ip, p.ttl)).collect();
+                        format!("{}=[{}]", label, recs.join(", "))
+                    }
+                    Err(e) => format!("{}=ERR({})", label, e),
                 }
-            }
+            })
+            .collect();
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+/// Consulta `name` (A y/o AAAA) en todos los resolvers configurados que
+/// apliquen a su zona, más `authoritative` si el proveedor lo conoce, en
+/// paralelo.
+#[instrument(name = "verify", skip_all, fields(name = %name))]
+pub async fn check_dns(
+    name: &str,
+    ipv4: bool,
+    ipv6: bool,
+    cfg: &DnsCheckSettings,
+    authoritative: Option<&str>,
+) -> DnsCheck {
+    let kinds: Vec<RecordType> = [(ipv4, RecordType::A), (ipv6, RecordType::AAAA)]
+        .into_iter()
+        .filter_map(|(wanted, kind)| wanted.then_some(kind))
+        .collect();
+    let timeout = Duration::from_millis(cfg.timeout_ms);
+    let provider_ns = authoritative.map(|server| ResolverConfig {
+        server: server.to_string(),
+        authoritative: true,
+        zone: None,
+    });
+    let resolvers: Vec<&ResolverConfig> = cfg
+        .resolvers
+        .iter()
+        .filter(|r| r.zone.as_deref().is_none_or(|z| in_zone(name, z)))
+        .chain(&provider_ns)
+        .collect();
+
+    let results = join_all(resolvers.iter().map(|r| query_resolver(r, name, &kinds, timeout))).await;
+    let answers = resolvers
+        .iter()
+        .zip(results)
+        .map(|(r, result)| ResolverAnswer {
+            resolver: r.server.clone(),
+            authoritative: r.authoritative,
+            result: result.map_err(|e| e.to_string()),
+        })
+        .collect();
+    DnsCheck {
+        name: name.to_string(),
+        answers,
+    }
+}
+
+fn in_zone(name: &str, zone: &str) -> bool {
+    let name = name.trim_end_matches('.');
+    let zone = zone.trim_end_matches('.');
+    name.eq_ignore_ascii_case(zone)
+        || name.to_ascii_lowercase().ends_with(&format!(".{}", zone.to_ascii_lowercase()))
+}
+
+async fn query_resolver(
+    resolver: &ResolverConfig,
+    name: &str,
+    kinds: &[RecordType],
+    timeout: Duration,
+) -> Result<Vec<Published>, Box<dyn std::error::Error + Send + Sync>> {
+    let server = if resolver.server == "system" {
+        system_resolver()?
+    } else {
+        resolve_server(&resolver.server, 53).await?
+    };
+    let mut qname = Name::from_ascii(name)?;
+    qname.set_fqdn(true);
+
+    let mut found = Vec::new();
+    for kind in kinds {
+        let mut msg = Message::new();
+        msg.set_id(rand::random())
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            // A un autoritativo se le pregunta sin recursión
+            .set_recursion_desired(!resolver.authoritative);
+        msg.add_query(Query::query(qname.clone(), *kind));
+
+        let raw = exchange(server, &msg.to_vec()?, timeout).await?;
+        let response = Message::from_vec(&raw)?;
+        match response.response_code() {
+            ResponseCode::NoError | ResponseCode::NXDomain => {}
+            code => return Err(format!("{} answered {}", server, code).into()),
+        }
+        for record in response.answers() {
+            let ip = match record.data() {
+                RData::A(a) => IpAddr::V4(a.0),
+                RData::AAAA(aaaa) => IpAddr::V6(aaaa.0),
+                _ => continue,
+            };
+            found.push(Published {
+                ip,
+                ttl: record.ttl(),
+            });
+        }
+    }
+    Ok(found)
+}
+
+/// Primer `nameserver` de /etc/resolv.conf.
+fn system_resolver() -> io::Result<SocketAddr> {
+    let conf = std::fs::read_to_string("/etc/resolv.conf")?;
+    parse_resolv_conf(&conf)
+        .map(|ip| SocketAddr::new(ip, 53))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No nameserver in /etc/resolv.conf"))
+}
+
+fn parse_resolv_conf(conf: &str) -> Option<IpAddr> {
+    conf.lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        // Ignora el scope de direcciones link-local ("fe80::1%eth0")
+        .filter_map(|rest| rest.trim().split('%').next()?.parse().ok())
+        .next()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn answer(resolver: &str, authoritative: bool, recs: &[(&str, u32)]) -> ResolverAnswer {
+        ResolverAnswer {
+            resolver: resolver.into(),
+            authoritative,
+            result: Ok(recs
+                .iter()
+                .map(|(ip, ttl)| Published {
+                    ip: ip.parse().unwrap(),
+                    ttl: *ttl,
+                })
+                .collect()),
         }
     }
-    None
+
+    #[test]
+    fn test_propagation() {
+        let new: IpAddr = "203.0.113.5".parse().unwrap();
+        let check = |answers| DnsCheck {
+            name: "casa.duckdns.org".into(),
+            answers,
+        };
+
+        let synced = check(vec![
+            answer("ns1", true, &[("203.0.113.5", 60)]),
+            answer("1.1.1.1", false, &[("203.0.113.5", 40)]),
+        ]);
+        assert_eq!(synced.propagation(new), Propagation::Synced);
+
+        let stale = check(vec![
+            answer("ns1", true, &[("203.0.113.5", 60)]),
+            answer("1.1.1.1", false, &[("198.51.100.7", 42)]),
+        ]);
+        assert_eq!(stale.propagation(new), Propagation::CachesStale { ttl: 42 });
+
+        let not_applied = check(vec![
+            answer("ns1", true, &[("198.51.100.7", 60)]),
+            answer("1.1.1.1", false, &[("203.0.113.5", 42)]),
+        ]);
+        assert_eq!(not_applied.propagation(new), Propagation::NotApplied);
+
+        // Sin autoritativos, caches con el valor viejo no prueban nada
+        let only_caches = check(vec![
+            answer("1.1.1.1", false, &[("198.51.100.7", 300)]),
+            answer("8.8.8.8", false, &[("198.51.100.7", 120)]),
+        ]);
+        assert_eq!(only_caches.propagation(new), Propagation::CachesStale { ttl: 300 });
+
+        let no_answers = check(vec![ResolverAnswer {
+            resolver: "ns1".into(),
+            authoritative: true,
+            result: Err("timed out".into()),
+        }]);
+        assert_eq!(no_answers.propagation(new), Propagation::Unknown);
+
+        let conf = "# generated\nsearch lan\nnameserver fe80::1%eth0\nnameserver 192.0.2.1\n";
+        assert_eq!(parse_resolv_conf(conf), Some("fe80::1".parse().unwrap()));
+    }
 }
@@ -0,0 +1,80 @@
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket, lookup_host};
+use tokio::time::timeout;
+
+/// Resuelve "host", "host:puerto", "ip" o "[ipv6]:puerto" a una dirección.
+pub async fn resolve_server(server: &str, default_port: u16) -> io::Result<SocketAddr> {
+    if let Ok(addr) = server.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    if let Ok(ip) = server.trim_matches(['[', ']']).parse::<std::net::IpAddr>() {
+        return Ok(SocketAddr::new(ip, default_port));
+    }
+    let target = if server.contains(':') {
+        server.to_string()
+    } else {
+        format!("{}:{}", server, default_port)
+    };
+    lookup_host(target)
+        .await?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Could not resolve {}", server)))
+}
+
+/// Envía un mensaje DNS ya serializado y devuelve la respuesta cruda.
+/// Usa UDP y repite por TCP si la respuesta llegó truncada.
+pub async fn exchange(server: SocketAddr, request: &[u8], wait: Duration) -> io::Result<Vec<u8>> {
+    let response = timeout(wait, exchange_udp(server, request))
+        .await
+        .map_err(|_| timed_out(server))??;
+    // Bit TC del header
+    if response.len() > 2 && response[2] & 0x02 != 0 {
+        return timeout(wait, exchange_tcp(server, request))
+            .await
+            .map_err(|_| timed_out(server))?;
+    }
+    Ok(response)
+}
+
+async fn exchange_udp(server: SocketAddr, request: &[u8]) -> io::Result<Vec<u8>> {
+    let bind: SocketAddr = if server.is_ipv4() {
+        "0.0.0.0:0".parse().unwrap()
+    } else {
+        "[::]:0".parse().unwrap()
+    };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(server).await?;
+    socket.send(request).await?;
+
+    let mut buf = vec![0u8; 4096];
+    loop {
+        let len = socket.recv(&mut buf).await?;
+        // Descarta respuestas a otras consultas (mismo ID)
+        if len >= 2 && request.len() >= 2 && buf[..2] == request[..2] {
+            buf.truncate(len);
+            return Ok(buf);
+        }
+    }
+}
+
+async fn exchange_tcp(server: SocketAddr, request: &[u8]) -> io::Result<Vec<u8>> {
+    let mut stream = TcpStream::connect(server).await?;
+    let len = u16::try_from(request.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "DNS message too large"))?;
+    stream.write_all(&len.to_be_bytes()).await?;
+    stream.write_all(request).await?;
+
+    let mut len_buf = [0u8; 2];
+    stream.read_exact(&mut len_buf).await?;
+    let mut buf = vec![0u8; u16::from_be_bytes(len_buf) as usize];
+    stream.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+fn timed_out(server: SocketAddr) -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, format!("DNS query to {} timed out", server))
+}
@@ -1,8 +1,11 @@
+use std::fmt;
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 use serde_json;
 use crate::models::entry::Entry;
 
+use super::provider::provider_for;
 use super::rutas::config_file;
 
 #[allow(unused)]
@@ -11,12 +14,17 @@ fn config_path() -> PathBuf {
 }
 #[allow(unused)]
 fn load_domains() -> Vec<Entry> {
+    read_domains().unwrap_or_default()
+}
+
+/// Lee `domainhdlr.json` distinguiendo un archivo inválido de uno vacío.
+pub fn read_domains() -> Result<Vec<Entry>, String> {
     let path = config_path();
     if !path.exists() {
-        return vec![];
+        return Ok(vec![]);
     }
-    let content = fs::read_to_string(path).unwrap_or_default();
-    serde_json::from_str(&content).unwrap_or_else(|_| vec![])
+    let content = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
 }
 #[allow(unused)]
 fn save_domains(entries: &[Entry]) {
@@ -28,30 +36,26 @@ fn save_domains(entries: &[Entry]) {
     let _ = fs::write(path, json);
 }
 #[allow(unused)]
-pub fn add_domain(name: &String, token: &String, activated: Option<bool>, txt: Option<String>) {
+pub fn add_domain(entry: Entry) {
     let mut entries = load_domains();
+    let name = entry.name.clone();
 
-    if entries.iter().any(|e| e.name == *name) {
+    if entries.iter().any(|e| e.name == name) {
         println!("Domain '{}' already exists. Use delete first if you want to replace it.", name);
         return;
     }
 
-    entries.push(Entry {
-        name: name.clone(),
-        token: token.clone(),
-        activated: activated.unwrap_or(true),
-        txt,
-    });
+    entries.push(entry);
 
     save_domains(&entries);
     println!("Domain '{}' added.", name);
 }
 
 #[allow(unused)]
-pub fn delete_domain(name: &String) {
+pub fn delete_domain(name: &str) {
     let mut entries = load_domains();
     let initial_len = entries.len();
-    entries.retain(|e| e.name != *name);
+    entries.retain(|e| e.name != name);
     save_domains(&entries);
     if entries.len() < initial_len {
         println!("Domain '{}' deleted.", name);
@@ -59,16 +63,161 @@ pub fn delete_domain(name: &String) {
         println!("Domain '{}' not found.", name);
     }
 }
+/// Activa o desactiva un dominio en `domainhdlr.json`.
+pub fn set_activated(name: &str, activated: bool) {
+    let mut entries = load_domains();
+    if let Some(e) = entries.iter_mut().find(|e| e.name == name) {
+        e.activated = activated;
+        save_domains(&entries);
+    }
+}
+
 #[allow(unused)]
 pub fn list_domains() -> Vec<Entry> {
     let entries = load_domains();
     for e in &entries {
         println!(
-            "- {} (active: {}, txt: {})",
+            "- {} [{}] (active: {}, txt: {})",
             e.name,
+            e.provider.name(),
             e.activated,
             e.txt.as_deref().unwrap_or("None")
         );
     }
     entries
 }
+
+/// Publica (o con `None` borra) el TXT de un dominio usando su proveedor.
+pub async fn set_domain_txt(name: &str, value: Option<&str>, timeout: Duration) {
+    let entries = load_domains();
+    let Some(entry) = entries.iter().find(|e| e.name == name) else {
+        println!("Domain '{}' not found.", name);
+        return;
+    };
+
+    let provider = provider_for(entry, timeout);
+    let result = match value {
+        Some(v) => provider.set_txt(entry, v).await,
+        None => provider.clear_txt(entry).await,
+    };
+    match result {
+        Ok(res) if res.success => println!("TXT for '{}' updated: {}", name, res.detail),
+        Ok(res) => println!("Provider {} rejected TXT for '{}': {}", provider.name(), name, res.detail),
+        Err(e) => println!("Failed to update TXT for '{}': {}", name, e),
+    }
+}
+
+/// Un cambio en la lista de dominios entre dos lecturas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryChange {
+    Added(String),
+    Removed(String),
+    /// Campos que cambiaron, ya formateados para el log.
+    Modified(String, Vec<String>),
+}
+
+impl EntryChange {
+    /// Dominio que hay que actualizar ya por este cambio.
+    pub fn needs_update(&self) -> Option<&str> {
+        match self {
+            EntryChange::Added(name) | EntryChange::Modified(name, _) => Some(name),
+            EntryChange::Removed(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for EntryChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryChange::Added(name) => write!(f, "added {}", name),
+            EntryChange::Removed(name) => write!(f, "removed {}", name),
+            EntryChange::Modified(name, fields) => write!(f, "modified {}: {}", name, fields.join(", ")),
+        }
+    }
+}
+
+/// Compara dos listas de dominios campo por campo. Los secretos (token y
+/// credenciales del proveedor) se informan sin su valor.
+pub fn diff_domains(old: &[Entry], new: &[Entry]) -> Vec<EntryChange> {
+    let mut changes = Vec::new();
+    for entry in new {
+        let Some(prev) = old.iter().find(|o| o.name == entry.name) else {
+            changes.push(EntryChange::Added(entry.name.clone()));
+            continue;
+        };
+        let mut fields = Vec::new();
+        if prev.activated != entry.activated {
+            fields.push(format!("activated {} -> {}", prev.activated, entry.activated));
+        }
+        if prev.ipv4 != entry.ipv4 {
+            fields.push(format!("ipv4 {} -> {}", prev.ipv4, entry.ipv4));
+        }
+        if prev.ipv6 != entry.ipv6 {
+            fields.push(format!("ipv6 {} -> {}", prev.ipv6, entry.ipv6));
+        }
+        if prev.txt != entry.txt {
+            fields.push(format!("txt {:?} -> {:?}", prev.txt, entry.txt));
+        }
+        if prev.provider.name() != entry.provider.name() {
+            fields.push(format!("provider {} -> {}", prev.provider.name(), entry.provider.name()));
+        } else if prev.provider != entry.provider {
+            fields.push(format!("{} settings changed", entry.provider.name()));
+        }
+        if !fields.is_empty() {
+            changes.push(EntryChange::Modified(entry.name.clone(), fields));
+        }
+    }
+    for entry in old {
+        if !new.iter().any(|n| n.name == entry.name) {
+            changes.push(EntryChange::Removed(entry.name.clone()));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::entry::ProviderConfig;
+
+    fn entry(name: &str, token: &str) -> Entry {
+        Entry {
+            name: name.into(),
+            activated: true,
+            txt: None,
+            provider: ProviderConfig::DuckDns { token: token.into() },
+            ipv4: true,
+            ipv6: false,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_diff_domains_per_field() {
+        let old = vec![entry("casa", "t1"), entry("oficina", "t1"), entry("viejo", "t1")];
+        let mut changed = entry("casa", "t2");
+        changed.activated = false;
+        changed.txt = Some("hola".into());
+        let new = vec![changed, entry("oficina", "t1"), entry("nuevo", "t1")];
+
+        let changes = diff_domains(&old, &new);
+        assert_eq!(
+            changes,
+            vec![
+                EntryChange::Modified(
+                    "casa".into(),
+                    vec![
+                        "activated true -> false".into(),
+                        "txt None -> Some(\"hola\")".into(),
+                        "duckdns settings changed".into()
+                    ]
+                ),
+                EntryChange::Added("nuevo".into()),
+                EntryChange::Removed("viejo".into()),
+            ]
+        );
+        let pending: Vec<_> = changes.iter().filter_map(|c| c.needs_update()).collect();
+        assert_eq!(pending, ["casa", "nuevo"]);
+        assert!(diff_domains(&new, &new).is_empty());
+    }
+}
@@ -1,118 +1,473 @@
-use reqwest::{Client, Response};
-use std::{error::Error, process::Command};
-use crate::process::logger::entry_for_errorlog;
-
-#[allow(unused)]
-pub fn get_public_ip() -> Result<String, Box<dyn Error>> {
-    let output = if cfg!(target_os = "windows") {
-        Command::new("nslookup")
-            .arg("myip.opendns.com")
-            .arg("resolver1.opendns.com")
-            .output()?
-    } else {
-        Command::new("dig")
-            .arg("+short")
-            .arg("myip.opendns.com")
-            .arg("@resolver1.opendns.com")
-            .output()?
-    };
+use async_trait::async_trait;
+use reqwest::Response;
+use std::error::Error;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+use tracing::{error, instrument};
+use crate::models::entry::{Entry, ProviderConfig};
+use crate::process::http_client::http_client;
+use crate::process::provider::{Addresses, DnsProvider, FailureAction, ProviderResult, UpdateResponse};
 
-    if output.status.success() {
-        let ip = String::from_utf8(output.stdout)?.trim().to_string();
-        Ok(ip)
-    } else {
-        Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Failed to get public IP",
-        )))
+/// Actualiza el dominio. Sin `ip` DuckDNS detecta la IPv4 de origen.
+#[instrument(name = "duckdns", skip_all, fields(domain = %domain))]
+pub async fn send_update(domain: &str, ip: Option<&str>, ipv6: Option<&str>, token: &str, txt: Option<String>, timeout: Duration) -> Result<Response, Box<dyn Error + Send + Sync>> {
+    let mut url = format!("https://www.duckdns.org/update?domains={}&token={}",domain,token);
+    if let Some(reg) = txt {
+        url.push_str(&format!("&txt={}",reg));
+    }
+    if let Some(ip) = ip {
+        url.push_str(&format!("&ip={}",ip));
     }
+    if let Some(ipv6) = ipv6 {
+        url.push_str(&format!("&ipv6={}",ipv6));
+    }
+    url.push_str("&verbose=true");
+    duck_get(domain, &url, timeout).await
 }
 
-#[allow(dead_code)]
-pub async fn send_update(domain: &str, ip : &str, token: &str,txt :Option<String>) -> Result<Response, Box<dyn Error>> {
-    // Initialize the HTTP client
-    let client = Client::new();
-
+pub async fn send_update_no_ip(domain: &str, token: &str,txt :Option<String>, timeout: Duration) -> Result<Response, Box<dyn Error + Send + Sync>> {
     let url = match txt {
         Some(reg) => {
-            format!("https://www.duckdns.org/update?domains={}&token={}&txt={}&ip={}&verbose=true",domain,token,reg,ip)
+            format!("https://www.duckdns.org/update?domains={}&token={}&txt={}&verbose=true",domain,token,reg)
         },
         None =>{
-            format!("https://www.duckdns.org/update?domains={}&token={}&ip={}&verbose=true",domain,token,ip)
+            format!("https://www.duckdns.org/update?domains={}&token={}&verbose=true",domain,token)
         }
     };
-
-    println!("{}",&url);
-    // Send the POST request
-    let res = client
-        .get(&url)
-        .send()
-        .await?;
-
-    // Handle the response
-    if res.status().is_success() == false  {
-        let _ = entry_for_errorlog(&format!(r#"**************************************************
-Error al actualizar Dominio {}. 
-Detalles:
-{:?}
-**************************************************"#,domain,res), false);
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Request failed with status: {}", res.status()),
-        )))
-    }
-    Ok(res)
+    duck_get(domain, &url, timeout).await
 }
 
-#[allow(dead_code)]
-pub async fn send_update_no_ip(domain: &str, token: &str,txt :Option<String>) -> Result<Response, Box<dyn Error>> {
-    // Initialize the HTTP client
-    let client = Client::new();
-
+/// Actualiza solo el TXT. Con `None` lo borra.
+pub async fn send_txt_update(domain: &str, token: &str, txt: Option<&str>, timeout: Duration) -> Result<Response, Box<dyn Error + Send + Sync>> {
     let url = match txt {
         Some(reg) => {
             format!("https://www.duckdns.org/update?domains={}&token={}&txt={}&verbose=true",domain,token,reg)
         },
-        None =>{
-            format!("https://www.duckdns.org/update?domains={}&token={}&verbose=true",domain,token)
+        None => {
+            format!("https://www.duckdns.org/update?domains={}&token={}&txt=&clear=true&verbose=true",domain,token)
         }
     };
+    duck_get(domain, &url, timeout).await
+}
+
+async fn duck_get(domain: &str, url: &str, timeout: Duration) -> Result<Response, Box<dyn Error + Send + Sync>> {
+    // Cliente compartido
+    let client = http_client();
 
-    println!("{}",&url);
-    // Send the POST request
+    // Send the GET request
     let res = client
-        .get(&url)
+        .get(url)
+        .timeout(timeout)
         .send()
         .await?;
 
     // Handle the response
-    if res.status().is_success() == false  {
-        let _ = entry_for_errorlog(&format!(r#"**************************************************
-Error al actualizar Dominio {}. 
-Detalles:
-{:?}
-**************************************************"#,domain,res), false);
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Request failed with status: {}", res.status()),
-        )))
+    if !res.status().is_success() {
+        error!(domain = %domain, "DuckDNS answered HTTP {}", res.status());
+        return Err(Box::new(std::io::Error::other(format!(
+            "Request failed with status: {}",
+            res.status()
+        ))));
     }
     Ok(res)
 }
 
+/// Primera línea de la respuesta de DuckDNS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuckStatus {
+    Ok,
+    /// Token o dominio inválidos; DuckDNS no da más detalle.
+    Ko,
+}
+
+/// Cuarta línea de la respuesta verbose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuckChange {
+    Updated,
+    NoChange,
+}
+
+/// Respuesta de `update?verbose=true`:
+/// `OK` / IPv4 registrada / IPv6 registrada / `UPDATED` o `NOCHANGE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuckResponse {
+    pub status: DuckStatus,
+    pub ipv4: Option<Ipv4Addr>,
+    pub ipv6: Option<Ipv6Addr>,
+    pub change: Option<DuckChange>,
+}
+
+impl DuckResponse {
+    pub fn parse(body: &str) -> Result<Self, String> {
+        let mut lines = body.lines().map(str::trim);
+        let status = match lines.next() {
+            Some("OK") => DuckStatus::Ok,
+            Some("KO") => DuckStatus::Ko,
+            _ => return Err(format!("Unexpected DuckDNS response {:?}", body)),
+        };
+        let ipv4 = match lines.next().unwrap_or_default() {
+            "" => None,
+            ip => Some(ip.parse().map_err(|_| format!("DuckDNS reported '{}', not an IPv4 address", ip))?),
+        };
+        let ipv6 = match lines.next().unwrap_or_default() {
+            "" => None,
+            ip => Some(ip.parse().map_err(|_| format!("DuckDNS reported '{}', not an IPv6 address", ip))?),
+        };
+        let change = match lines.next().unwrap_or_default() {
+            "" => None,
+            "UPDATED" => Some(DuckChange::Updated),
+            "NOCHANGE" => Some(DuckChange::NoChange),
+            other => return Err(format!("Unexpected DuckDNS change flag '{}'", other)),
+        };
+        Ok(Self {
+            status,
+            ipv4,
+            ipv6,
+            change,
+        })
+    }
+
+    /// Diferencias entre lo enviado y lo que DuckDNS dice haber registrado.
+    pub fn mismatches(&self, sent: &Addresses) -> Vec<String> {
+        let mut out = Vec::new();
+        if let Some(ip) = sent.ipv4
+            && self.ipv4 != Some(ip)
+        {
+            out.push(format!("sent IPv4 {} but DuckDNS recorded {:?}", ip, self.ipv4));
+        }
+        if let Some(ip) = sent.ipv6
+            && self.ipv6 != Some(ip)
+        {
+            out.push(format!("sent IPv6 {} but DuckDNS recorded {:?}", ip, self.ipv6));
+        }
+        out
+    }
+}
+
+impl fmt::Display for DuckResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let change = match self.change {
+            Some(DuckChange::Updated) => "UPDATED",
+            Some(DuckChange::NoChange) => "NOCHANGE",
+            None => "-",
+        };
+        let v4 = self.ipv4.map(|ip| ip.to_string()).unwrap_or("-".into());
+        let v6 = self.ipv6.map(|ip| ip.to_string()).unwrap_or("-".into());
+        write!(f, "{:?} {} ipv4={} ipv6={}", self.status, change, v4, v6)
+    }
+}
+
+/// Respuesta de `update?txt=...&verbose=true`:
+/// `OK` / TXT registrado (vacío si se borró) / `UPDATED` o `NOCHANGE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuckTxtResponse {
+    pub status: DuckStatus,
+    pub txt: String,
+    pub change: Option<DuckChange>,
+}
+
+impl DuckTxtResponse {
+    pub fn parse(body: &str) -> Result<Self, String> {
+        let mut lines = body.lines().map(str::trim);
+        let status = match lines.next() {
+            Some("OK") => DuckStatus::Ok,
+            Some("KO") => DuckStatus::Ko,
+            _ => return Err(format!("Unexpected DuckDNS response {:?}", body)),
+        };
+        let txt = lines.next().unwrap_or_default().to_string();
+        let change = match lines.next().unwrap_or_default() {
+            "" => None,
+            "UPDATED" => Some(DuckChange::Updated),
+            "NOCHANGE" => Some(DuckChange::NoChange),
+            other => return Err(format!("Unexpected DuckDNS change flag '{}'", other)),
+        };
+        Ok(Self { status, txt, change })
+    }
+}
+
+/// Backend DuckDNS. El token sale de la config de cada entrada.
+pub struct DuckDns {
+    timeout: Duration,
+}
+
+/// Token de DuckDNS de la entrada; vacío si es de otro proveedor.
+fn token(entry: &Entry) -> &str {
+    match &entry.provider {
+        ProviderConfig::DuckDns { token } => token,
+        _ => "",
+    }
+}
+
+impl DuckDns {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+
+    /// Interpreta el cuerpo de una respuesta de `/update`.
+    fn parse_response(&self, body: &str) -> UpdateResponse {
+        match DuckResponse::parse(body) {
+            Ok(r) if r.status == DuckStatus::Ok => UpdateResponse::ok(r.to_string()),
+            // Reintentar con el mismo token no lo arregla
+            Ok(_) => UpdateResponse::failed(
+                "KO: DuckDNS rejected the token or domain",
+                FailureAction::Disable,
+            ),
+            Err(e) => UpdateResponse::failed(e, FailureAction::Alert),
+        }
+    }
+
+    /// Un solo pedido para `domains` ("a" o "a,b,c") y verifica lo registrado.
+    async fn update_domains(&self, domains: &str, token: &str, txt: Option<String>, addrs: &Addresses) -> ProviderResult<UpdateResponse> {
+        let ipv4 = addrs.ipv4.map(|ip| ip.to_string());
+        let ipv6 = addrs.ipv6.map(|ip| ip.to_string());
+        let res = send_update(domains, ipv4.as_deref(), ipv6.as_deref(), token, txt, self.timeout).await?;
+        let body = res.text().await?;
+        let response = self.parse_response(&body);
+        if !response.success {
+            return Ok(response);
+        }
+
+        // Confirmar que registró lo que mandamos
+        let parsed = DuckResponse::parse(&body)?;
+        let mismatches = parsed.mismatches(addrs);
+        if mismatches.is_empty() {
+            Ok(response)
+        } else {
+            Ok(UpdateResponse::failed(
+                format!("{} | {}", parsed, mismatches.join("; ")),
+                FailureAction::Alert,
+            ))
+        }
+    }
+
+    /// Interpreta la respuesta de un cambio de TXT y confirma que DuckDNS
+    /// registró `expected` (vacío al borrar).
+    fn parse_txt_response(&self, body: &str, expected: &str) -> UpdateResponse {
+        match DuckTxtResponse::parse(body) {
+            Ok(r) if r.status == DuckStatus::Ko => UpdateResponse::failed(
+                "KO: DuckDNS rejected the token or domain",
+                FailureAction::Disable,
+            ),
+            Ok(r) if r.txt != expected => UpdateResponse::failed(
+                format!("sent TXT {:?} but DuckDNS recorded {:?}", expected, r.txt),
+                FailureAction::Alert,
+            ),
+            Ok(r) => UpdateResponse::ok(format!("OK txt={:?}", r.txt)),
+            Err(e) => UpdateResponse::failed(e, FailureAction::Alert),
+        }
+    }
+}
+
+/// DuckDNS aplica el mismo token, TXT e IPs a todos los dominios del
+/// pedido. Las entradas sin IPv4 (deshabilitada o no detectada) van
+/// solas: `update` las rechaza.
+fn batch_key(entry: &Entry, addrs: &Addresses) -> Option<String> {
+    (entry.ipv4 && addrs.ipv4.is_some()).then(|| format!("duckdns|{}|{:?}|{}", token(entry), entry.txt, addrs))
+}
+
+/// Agrupa índices de `entries` que pueden ir en un mismo `domains=a,b,c`.
+fn batches(entries: &[(&Entry, Addresses)]) -> Vec<Vec<usize>> {
+    let mut batches: Vec<(Option<String>, Vec<usize>)> = Vec::new();
+    for (i, (entry, addrs)) in entries.iter().enumerate() {
+        let key = batch_key(entry, addrs);
+        match batches.iter_mut().find(|(k, _)| key.is_some() && *k == key) {
+            Some((_, batch)) => batch.push(i),
+            None => batches.push((key, vec![i])),
+        }
+    }
+    batches.into_iter().map(|(_, batch)| batch).collect()
+}
+
+#[async_trait]
+impl DnsProvider for DuckDns {
+    fn name(&self) -> &'static str {
+        "duckdns"
+    }
+
+    async fn update(&self, entry: &Entry, addrs: &Addresses) -> ProviderResult<UpdateResponse> {
+        // Sin `ip=` DuckDNS pisa el A con la IPv4 de origen
+        if !entry.ipv4 {
+            return Ok(UpdateResponse::failed(
+                "DuckDNS always publishes an A record; ipv4 cannot be disabled",
+                FailureAction::Disable,
+            ));
+        }
+        if addrs.ipv4.is_none() {
+            return Ok(UpdateResponse::failed(
+                "No IPv4 detected; DuckDNS would publish the source address",
+                FailureAction::Retry,
+            ));
+        }
+        self.update_domains(&entry.name, token(entry), entry.txt.clone(), addrs).await
+    }
+
+    fn batch_key(&self, entry: &Entry, addrs: &Addresses) -> Option<String> {
+        batch_key(entry, addrs)
+    }
+
+    async fn update_many(&self, entries: &[(&Entry, Addresses)]) -> Vec<ProviderResult<UpdateResponse>> {
+        let mut results: Vec<Option<ProviderResult<UpdateResponse>>> = entries.iter().map(|_| None).collect();
+        for batch in batches(entries) {
+            let (first, addrs) = &entries[batch[0]];
+            if batch.len() == 1 {
+                results[batch[0]] = Some(self.update(first, addrs).await);
+                continue;
+            }
+
+            let names: Vec<&str> = batch.iter().map(|&i| entries[i].0.name.as_str()).collect();
+            let names = names.join(",");
+            match self.update_domains(&names, token(first), first.txt.clone(), addrs).await {
+                // Un KO no dice qué dominio falló: se repite uno por uno
+                // para desactivar solo el que corresponde
+                Ok(r) if !r.success && r.on_failure == FailureAction::Disable => {
+                    for &i in &batch {
+                        results[i] = Some(self.update(entries[i].0, &entries[i].1).await);
+                    }
+                }
+                Ok(r) => {
+                    for &i in &batch {
+                        results[i] = Some(Ok(UpdateResponse {
+                            detail: format!("{} (batch: {})", r.detail, names),
+                            ..r.clone()
+                        }));
+                    }
+                }
+                Err(e) => {
+                    let msg = e.to_string();
+                    for &i in &batch {
+                        results[i] = Some(Err(msg.clone().into()));
+                    }
+                }
+            }
+        }
+        results
+            .into_iter()
+            .map(|r| r.expect("every entry belongs to a batch"))
+            .collect()
+    }
+
+    async fn set_txt(&self, entry: &Entry, value: &str) -> ProviderResult<UpdateResponse> {
+        let res = send_txt_update(&entry.name, token(entry), Some(value), self.timeout).await?;
+        let body = res.text().await?;
+        Ok(self.parse_txt_response(&body, value))
+    }
+
+    async fn clear_txt(&self, entry: &Entry) -> ProviderResult<UpdateResponse> {
+        let res = send_txt_update(&entry.name, token(entry), None, self.timeout).await?;
+        let body = res.text().await?;
+        Ok(self.parse_txt_response(&body, ""))
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::send_update;
+    use std::time::Duration;
+
+    use super::{DuckChange, DuckDns, DuckResponse, DuckStatus, DuckTxtResponse, batches, send_update};
+    use crate::process::provider::FailureAction;
+    use crate::models::entry::{Entry, ProviderConfig};
+    use crate::process::provider::Addresses;
+    use crate::models::settings::IpDetectionSettings;
+    use crate::process::ip_source::detect_public_ip;
     #[tokio::test]
     async fn test_update(){
-        let ip = super::get_public_ip().unwrap();
-        send_update("ptechcloud",&ip, "b2f08b23-ebf1-4d4b-8132-327b9def1bf1" ,Some("google-site-verification=8ITNQvEh75v-MRsYr-sq3409kPQxeeus80vv8isjkms".to_string()) ).await.unwrap();
+        let ip = detect_public_ip(&IpDetectionSettings::default(), false).await.ip.unwrap();
+        send_update("ptechcloud",Some(&ip.to_string()), None, "b2f08b23-ebf1-4d4b-8132-327b9def1bf1" ,Some("google-site-verification=8ITNQvEh75v-MRsYr-sq3409kPQxeeus80vv8isjkms".to_string()), Duration::from_secs(15)).await.unwrap();
 
     }
 
-    #[test]
-    fn test_get_ip(){
-        let ip = super::get_public_ip().unwrap();
+    #[tokio::test]
+    async fn test_get_ip(){
+        let ip = detect_public_ip(&IpDetectionSettings::default(), false).await.ip.unwrap();
         println!("{}",ip);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_verbose() {
+        let r = DuckResponse::parse("OK\n203.0.113.7\n2001:db8::7\nUPDATED").unwrap();
+        assert_eq!(r.status, DuckStatus::Ok);
+        assert_eq!(r.ipv4, Some("203.0.113.7".parse().unwrap()));
+        assert_eq!(r.ipv6, Some("2001:db8::7".parse().unwrap()));
+        assert_eq!(r.change, Some(DuckChange::Updated));
+
+        let sent = Addresses {
+            ipv4: Some("198.51.100.1".parse().unwrap()),
+            ipv6: Some("2001:db8::7".parse().unwrap()),
+        };
+        assert_eq!(r.mismatches(&sent).len(), 1);
+
+        let nochange = DuckResponse::parse("OK\n203.0.113.7\n\nNOCHANGE").unwrap();
+        assert_eq!(nochange.ipv6, None);
+        assert_eq!(nochange.change, Some(DuckChange::NoChange));
+
+        assert_eq!(DuckResponse::parse("KO").unwrap().status, DuckStatus::Ko);
+        assert!(DuckResponse::parse("<html>").is_err());
+    }
+
+    #[test]
+    fn test_parse_txt_verbose() {
+        let body = "OK\ngoogle-site-verification=8ITNQvEh75v\nUPDATED";
+        let r = DuckTxtResponse::parse(body).unwrap();
+        assert_eq!(r.status, DuckStatus::Ok);
+        assert_eq!(r.txt, "google-site-verification=8ITNQvEh75v");
+        assert_eq!(r.change, Some(DuckChange::Updated));
+        let duck = DuckDns::new(Duration::from_secs(5));
+        assert!(duck.parse_txt_response(body, "google-site-verification=8ITNQvEh75v").success);
+
+        let stale = duck.parse_txt_response(body, "otro-valor");
+        assert_eq!(stale.on_failure, FailureAction::Alert);
+
+        let cleared = "OK\n\nUPDATED";
+        assert!(duck.parse_txt_response(cleared, "").success);
+        assert_eq!(duck.parse_txt_response("KO", "").on_failure, FailureAction::Disable);
+    }
+
+    #[test]
+    fn test_batches_by_token_txt_and_ip() {
+        let entry = |name: &str, token: &str, txt: Option<&str>| Entry {
+            name: name.into(),
+            activated: true,
+            txt: txt.map(String::from),
+            provider: ProviderConfig::DuckDns { token: token.into() },
+            ipv4: true,
+            ipv6: false,
+            tags: Vec::new(),
+        };
+        let v4 = Addresses {
+            ipv4: Some("203.0.113.7".parse().unwrap()),
+            ipv6: None,
+        };
+        let (a, b, c, d) = (
+            entry("a", "t1", None),
+            entry("b", "t1", None),
+            entry("c", "t2", None),
+            entry("d", "t1", Some("verify")),
+        );
+        let e = entry("e", "t1", None);
+        let mut f = entry("f", "t1", None);
+        f.ipv4 = false;
+        let g = f.clone();
+        // Doble pila sin IPv4 detectada en este ciclo
+        let v6 = Addresses {
+            ipv4: None,
+            ipv6: Some("2001:db8::7".parse().unwrap()),
+        };
+        let (h, i) = (entry("h", "t1", None), entry("i", "t1", None));
+        let entries = vec![
+            (&a, v4),
+            (&b, v4),
+            (&c, v4),
+            (&d, v4),
+            (&e, Addresses::default()),
+            (&f, Addresses::default()),
+            (&g, Addresses::default()),
+            (&h, v6),
+            (&i, v6),
+        ];
+        assert_eq!(
+            batches(&entries),
+            vec![vec![0, 1], vec![2], vec![3], vec![4], vec![5], vec![6], vec![7], vec![8]]
+        );
+    }
+}
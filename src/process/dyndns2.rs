@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::Client;
+
+use crate::models::entry::Entry;
+use crate::process::http_client::http_client;
+use crate::process::provider::{Addresses, DnsProvider, FailureAction, ProviderResult, UpdateResponse};
+
+/// Los proveedores dyndns2 exigen un User-Agent identificable.
+const USER_AGENT: &str = concat!("PTechSoftware-domainhdlr/", env!("CARGO_PKG_VERSION"));
+
+/// Cliente genérico del protocolo dyndns2 (`/nic/update`), usado por
+/// No-IP, Dynu, OVH, Google Domains y otros.
+pub struct DynDns2 {
+    client: Client,
+    server: String,
+    username: String,
+    password: String,
+    timeout: Duration,
+}
+
+impl DynDns2 {
+    /// `server` es la base del endpoint, ej. `https://dynupdate.no-ip.com`.
+    pub fn new(server: &str, username: &str, password: &str, timeout: Duration) -> Self {
+        Self {
+            client: http_client().clone(),
+            server: server.trim_end_matches('/').to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            timeout,
+        }
+    }
+
+    /// Interpreta el cuerpo de la respuesta de `/nic/update`.
+    fn parse_response(&self, body: &str) -> UpdateResponse {
+        let body = body.trim();
+        // Con un solo hostname hay una sola línea: "<código> [ip]".
+        let code = body.split_whitespace().next().unwrap_or("");
+        match code {
+            "good" | "nochg" => UpdateResponse::ok(body),
+            "911" | "dnserr" => UpdateResponse::failed(body, FailureAction::Retry),
+            "badauth" | "nohost" | "notfqdn" | "numhost" | "badagent" | "!donator"
+            | "!yours" | "abuse" => UpdateResponse::failed(body, FailureAction::Disable),
+            _ => UpdateResponse::failed(body, FailureAction::Alert),
+        }
+    }
+}
+
+#[async_trait]
+impl DnsProvider for DynDns2 {
+    fn name(&self) -> &'static str {
+        "dyndns2"
+    }
+
+    /// dyndns2 no maneja TXT: `entry.txt` se ignora. Con ambas familias
+    /// se envía `myip=v4,v6`, la extensión que aceptan No-IP y Dynu.
+    async fn update(&self, entry: &Entry, addrs: &Addresses) -> ProviderResult<UpdateResponse> {
+        let url = format!("{}/nic/update", self.server);
+        let ip = addrs.to_string().replace(", ", ",");
+        let res = self
+            .client
+            .get(&url)
+            .query(&[("hostname", entry.name.as_str()), ("myip", ip.as_str())])
+            .basic_auth(&self.username, Some(&self.password))
+            .header(reqwest::header::USER_AGENT, USER_AGENT)
+            .timeout(self.timeout)
+            .send()
+            .await?;
+        // Algunos servidores responden `badauth` con 401: el cuerpo manda.
+        let body = res.text().await?;
+        Ok(self.parse_response(&body))
+    }
+
+    async fn set_txt(&self, _entry: &Entry, _value: &str) -> ProviderResult<UpdateResponse> {
+        Err("TXT records are not supported by the dyndns2 protocol".into())
+    }
+
+    async fn clear_txt(&self, _entry: &Entry) -> ProviderResult<UpdateResponse> {
+        Err("TXT records are not supported by the dyndns2 protocol".into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::entry::ProviderConfig;
+    use mockito::Matcher;
+
+    fn entry() -> Entry {
+        Entry {
+            name: "home.example.net".into(),
+            activated: true,
+            txt: None,
+            ipv4: true,
+            ipv6: false,
+            tags: Vec::new(),
+            provider: ProviderConfig::DynDns2 {
+                server: String::new(),
+                username: "user".into(),
+                password: "pass".into(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_sends_basic_auth_and_accepts_good() {
+        let mut server = mockito::Server::new_async().await;
+        let update = server
+            .mock("GET", "/nic/update")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("hostname".into(), "home.example.net".into()),
+                Matcher::UrlEncoded("myip".into(), "198.51.100.4".into()),
+            ]))
+            // base64("user:pass")
+            .match_header("authorization", "Basic dXNlcjpwYXNz")
+            .with_body("good 198.51.100.4")
+            .create_async()
+            .await;
+
+        let client = DynDns2::new(&server.url(), "user", "pass", Duration::from_secs(5));
+        let addrs = Addresses {
+            ipv4: Some("198.51.100.4".parse().unwrap()),
+            ipv6: None,
+        };
+        let res = client.update(&entry(), &addrs).await.unwrap();
+
+        assert!(res.success);
+        update.assert_async().await;
+    }
+
+    #[test]
+    fn test_return_codes_map_to_actions() {
+        let client = DynDns2::new("http://localhost", "u", "p", Duration::from_secs(5));
+        assert!(client.parse_response("nochg 198.51.100.4\n").success);
+        assert_eq!(client.parse_response("911").on_failure, FailureAction::Retry);
+        assert_eq!(client.parse_response("badauth").on_failure, FailureAction::Disable);
+        assert_eq!(client.parse_response("nohost").on_failure, FailureAction::Disable);
+        assert_eq!(client.parse_response("abuse").on_failure, FailureAction::Disable);
+        assert_eq!(client.parse_response("<html>").on_failure, FailureAction::Alert);
+    }
+}
@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use lettre::message::{header, Mailbox, Message};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use std::time::Duration;
+use tracing::instrument;
+
+use crate::models::settings::MailConfig;
+use crate::process::notifier::{Alert, Notifier, NotifyResult};
+
+/// Canal de correo por SMTP.
+pub struct Email {
+    name: String,
+    cfg: MailConfig,
+}
+
+impl Email {
+    pub fn new(name: &str, cfg: &MailConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            cfg: cfg.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for Email {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send(&self, alert: &Alert) -> NotifyResult<()> {
+        send_email_alert(&self.cfg, &alert.subject, &alert.body).await
+    }
+}
+
+/// Envía un correo asincrónicamente usando Gmail y TLS.
+/// Devuelve `Ok(())` si el envío fue exitoso.
+#[instrument(name = "email", skip_all, fields(subject = %subject))]
+pub async fn send_email_alert(cfg: &MailConfig, subject: &str, body: &str) -> NotifyResult<()> {
+    // 📨 Construir el mensaje
+    let email = Message::builder()
+        .from(Mailbox::new(None, cfg.sender.parse()?))
+        .to(Mailbox::new(None, cfg.recipient.parse()?))
+        .subject(subject)
+        .header(header::ContentType::TEXT_PLAIN)
+        .body(body.to_string())?;
+
+    // 🔐 Autenticación
+    let creds = Credentials::new(cfg.sender.clone(), cfg.password.clone());
+
+    // 🚀 Transport con TLS (STARTTLS en puerto 587)
+    let mailer: AsyncSmtpTransport<Tokio1Executor> = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&cfg.smtp_server)?
+        .port(cfg.smtp_port)
+        .credentials(creds)
+        .timeout(Some(Duration::from_secs(10)))
+        .build();
+
+    // Enviar; el resultado lo registra quien despacha la alerta
+    mailer.send(email).await?;
+    Ok(())
+}
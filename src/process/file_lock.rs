@@ -1,74 +1,164 @@
-#[allow(dead_code)]
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
 
-use std::{io::ErrorKind, path::PathBuf, sync::Mutex};
-use tokio::fs;
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
 
-// El nombre del archivo que será eliminado
-const APP_LOCK_FILE: &str = "domain.lock"; 
+use super::rutas::config_dir;
 
+// El nombre del lock file
+const APP_LOCK_FILE: &str = "domain.lock";
 
-#[allow(dead_code, unused,non_upper_case_globals)]
-const lck: std::sync::Mutex<bool> = Mutex::new(true);
+/// Código de salida cuando ya hay otra instancia corriendo.
+pub const EXIT_ALREADY_RUNNING: i32 = 3;
 
-#[allow(dead_code, unused)]
 /// Construye la ruta completa al lock file.
-pub fn get_lock_path() -> Result<PathBuf, String> {
-    lck.lock();
-    // ... (lógica para encontrar el directorio de configuración) ...
-    let cfg_dir = dirs::config_dir()
-        .ok_or_else(|| "No se pudo determinar el directorio de configuración.".to_string())?;
-
-    let app_dir = cfg_dir.join("domainhdlr"); 
-    Ok(app_dir.join(APP_LOCK_FILE)) // -> Esta es la ruta del lock file
+pub fn get_lock_path() -> PathBuf {
+    config_dir().join(APP_LOCK_FILE)
 }
 
-#[allow(dead_code, unused)]
-///Elimina el lock file.
-pub async fn remove_cfg_file() -> Result<(), Box<dyn std::error::Error>> {
+/// Quién tiene el lock, guardado dentro del archivo.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub started_at: String,
+}
 
-    lck.lock();
-    // Obtiene la ruta del lock file.
-    let lock_path = get_lock_path().map_err(|e| e.to_string())?;
+#[derive(Debug)]
+pub enum LockError {
+    /// Otra instancia tiene el `flock`.
+    AlreadyRunning(Option<LockInfo>),
+    Io(io::Error),
+}
 
-    if lock_path.exists() {
-        // Usa fs::remove_file para eliminarlo.
-        fs::remove_file(&lock_path).await?;
-        println!("Lock file eliminado: {}", lock_path.display());
-    } else {
-        println!("Lock file no encontrado para eliminar: {}", lock_path.display());
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::AlreadyRunning(Some(info)) => write!(
+                f,
+                "Another instance is already running (pid {}, started {})",
+                info.pid, info.started_at
+            ),
+            LockError::AlreadyRunning(None) => write!(f, "Another instance is already running"),
+            LockError::Io(e) => write!(f, "Could not acquire the instance lock: {}", e),
+        }
     }
+}
+
+impl std::error::Error for LockError {}
 
-    Ok(())
+impl From<io::Error> for LockError {
+    fn from(e: io::Error) -> Self {
+        LockError::Io(e)
+    }
 }
 
+/// `flock` exclusivo sobre el lock file mientras viva el daemon.
+/// El kernel lo libera si el proceso muere, así que un archivo que
+/// quedó de un crash no bloquea el próximo arranque.
+#[derive(Debug)]
+pub struct InstanceLock {
+    file: File,
+    path: PathBuf,
+    /// Datos de una instancia anterior que terminó sin liberar el archivo.
+    pub recovered: Option<LockInfo>,
+}
 
-#[allow(dead_code)]
-pub async fn create_lock_file() -> Result<bool, Box<dyn std::error::Error>> {
-    let lock_path = get_lock_path().map_err(|e| e.to_string())?;
-    
-    // Aseguramos que el directorio de la aplicación exista
-    if let Some(parent) = lock_path.parent() {
-        fs::create_dir_all(parent).await?;
+impl InstanceLock {
+    pub fn acquire(started_at: &str) -> Result<Self, LockError> {
+        Self::acquire_at(&get_lock_path(), started_at)
     }
 
-    // Usamos OpenOptions para intentar crear el archivo de forma exclusiva.
-    // O_EXCL (exclusive) significa que fallará si el archivo ya existe.
-    match fs::OpenOptions::new()
-        .write(true)
-        .create_new(true) // <- Esta opción fuerza la creación exclusiva
-        .open(&lock_path)
-        .await 
-    {
-        Ok(_) => {
-            // Éxito: el archivo fue creado, bloqueo obtenido.
-            println!("✅ Lock file creado. Bloqueo adquirido en: {}", lock_path.display());
-            Ok(true) 
+    fn acquire_at(path: &Path, started_at: &str) -> Result<Self, LockError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        loop {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(path)?;
+
+            if let Err(e) = file.try_lock_exclusive() {
+                if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                    return Err(LockError::AlreadyRunning(read_info(&mut file)));
+                }
+                return Err(e.into());
+            }
+
+            // Si el archivo fue borrado o reemplazado mientras esperábamos,
+            // el lock quedó sobre un inode huérfano: reintentar
+            let same = fs::metadata(path)
+                .map(|m| m.ino() == file.metadata().map(|f| f.ino()).unwrap_or(0))
+                .unwrap_or(false);
+            if !same {
+                continue;
+            }
+
+            let recovered = read_info(&mut file);
+            let info = LockInfo {
+                pid: std::process::id(),
+                started_at: started_at.to_string(),
+            };
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&serde_json::to_vec(&info).map_err(io::Error::other)?)?;
+            file.sync_all()?;
+
+            return Ok(Self {
+                file,
+                path: path.to_path_buf(),
+                recovered,
+            });
         }
-        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
-            // Fallo: el archivo ya existe, otra instancia tiene el bloqueo.
-            println!("❌ El lock file ya existe. No se puede adquirir el bloqueo.");
-            Ok(false) 
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // Se borra antes de soltar el lock para que nadie lo tome a medias
+        let _ = fs::remove_file(&self.path);
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+fn read_info(file: &mut File) -> Option<LockInfo> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut content).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_single_instance_and_stale_recovery() {
+        let path = std::env::temp_dir().join(format!("domainhdlr-{}.lock", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let first = InstanceLock::acquire_at(&path, "t0").unwrap();
+        assert_eq!(first.recovered, None);
+        match InstanceLock::acquire_at(&path, "t1") {
+            Err(LockError::AlreadyRunning(Some(info))) => assert_eq!(info.pid, std::process::id()),
+            other => panic!("expected AlreadyRunning, got {:?}", other),
         }
-        Err(e) => Err(e.into()), // Otro error de E/S
+        drop(first);
+        assert!(!path.exists());
+
+        // Un archivo sin flock es de una instancia que murió
+        let stale = LockInfo {
+            pid: 999_999,
+            started_at: "ayer".into(),
+        };
+        fs::write(&path, serde_json::to_vec(&stale).unwrap()).unwrap();
+        let lock = InstanceLock::acquire_at(&path, "t2").unwrap();
+        assert_eq!(lock.recovered, Some(stale));
     }
-}
\ No newline at end of file
+}
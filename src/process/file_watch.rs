@@ -0,0 +1,76 @@
+use std::ffi::{CString, OsStr};
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::fd::FromRawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+
+/// Eventos que cuentan como "el archivo cambió". Se vigila el directorio
+/// y no el archivo porque los editores suelen guardar con un rename.
+const MASK: u32 = libc::IN_MODIFY | libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_CREATE | libc::IN_DELETE;
+
+/// Tamaño fijo de `struct inotify_event`, sin el nombre.
+const EVENT_HEADER: usize = 16;
+
+/// Vigila `path` con inotify. Cada cambio llega como un `()` por el canal;
+/// una ráfaga de escrituras genera varios, quien recibe los agrupa.
+pub fn watch_file(path: &Path) -> io::Result<UnboundedReceiver<()>> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?
+        .to_os_string();
+    std::fs::create_dir_all(dir)?;
+
+    let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // El File cierra el descriptor al salir del hilo
+    let mut inotify = unsafe { File::from_raw_fd(fd) };
+    let cdir = CString::new(dir.as_os_str().as_bytes()).map_err(io::Error::other)?;
+    if unsafe { libc::inotify_add_watch(fd, cdir.as_ptr(), MASK) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let (tx, rx) = unbounded_channel();
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = match inotify.read(&mut buf) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            };
+            let touched = event_names(&buf[..n]).iter().any(|ev| ev == &name);
+            // Sin receptor no tiene sentido seguir leyendo
+            if touched && tx.send(()).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
+
+/// Nombres de archivo de los eventos en un buffer leído del descriptor.
+fn event_names(buf: &[u8]) -> Vec<&OsStr> {
+    let mut names = Vec::new();
+    let mut offset = 0;
+    while offset + EVENT_HEADER <= buf.len() {
+        let len = u32::from_ne_bytes(buf[offset + 12..offset + 16].try_into().unwrap()) as usize;
+        let end = (offset + EVENT_HEADER + len).min(buf.len());
+        let raw = &buf[offset + EVENT_HEADER..end];
+        // El nombre viene relleno con NULs hasta alinear
+        let name = raw.split(|b| *b == 0).next().unwrap_or_default();
+        if !name.is_empty() {
+            names.push(OsStr::from_bytes(name));
+        }
+        offset = end;
+    }
+    names
+}
@@ -0,0 +1,18 @@
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use reqwest::Client;
+
+/// Cliente HTTP compartido: reutiliza conexiones y sesiones TLS entre
+/// pedidos. Los proveedores limitan cada pedido con `request_timeout_ms`.
+static CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .connect_timeout(Duration::from_secs(5))
+        .timeout(Duration::from_secs(30))
+        .build()
+        .expect("Could not build HTTP client")
+});
+
+pub fn http_client() -> &'static Client {
+    &CLIENT
+}
@@ -0,0 +1,182 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::public_ip::IpResult;
+
+/// Una dirección asignada a una interfaz, con los flags del kernel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IfAddress {
+    pub ip: IpAddr,
+    pub deprecated: bool,
+    /// DAD pendiente o fallido: todavía no es usable.
+    pub tentative: bool,
+    /// IPv6 temporal de privacidad (RFC 4941): rota seguido y no es para
+    /// recibir conexiones.
+    pub temporary: bool,
+}
+
+/// Dirección enrutable en Internet (no privada, link-local, CGNAT, etc.).
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_public_v4(v4),
+        IpAddr::V6(v6) => is_public_v6(v6),
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // 100.64.0.0/10, CGNAT
+        || (a == 100 && (64..128).contains(&b)))
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_unicast_link_local()
+        || ip.is_unique_local()
+        // 2001:db8::/32, documentación
+        || (ip.segments()[0] == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+/// Primera dirección pública y vigente de la familia pedida. Las
+/// temporales solo se usan si no hay una estable.
+pub fn pick_address(addresses: &[IfAddress], ipv6: bool) -> Option<IpAddr> {
+    addresses
+        .iter()
+        .filter(|a| a.ip.is_ipv6() == ipv6 && !a.deprecated && !a.tentative && is_public(a.ip))
+        .min_by_key(|a| a.temporary)
+        .map(|a| a.ip)
+}
+
+/// Lee la IP pública directamente de `interface`.
+pub async fn interface_ip(interface: &str, ipv6: bool) -> IpResult {
+    let addresses = list_addresses(interface).await?;
+    pick_address(&addresses, ipv6).ok_or_else(|| {
+        format!(
+            "No public {} address on {} (found: {:?})",
+            if ipv6 { "IPv6" } else { "IPv4" },
+            interface,
+            addresses.iter().map(|a| a.ip).collect::<Vec<_>>()
+        )
+        .into()
+    })
+}
+
+/// Direcciones de `interface` vía rtnetlink.
+#[cfg(target_os = "linux")]
+pub async fn list_addresses(interface: &str) -> Result<Vec<IfAddress>, Box<dyn std::error::Error + Send + Sync>> {
+    use futures::TryStreamExt;
+    use netlink_packet_route::address::Nla;
+    use netlink_packet_route::{
+        AF_INET, AF_INET6, IFA_F_DADFAILED, IFA_F_DEPRECATED, IFA_F_TEMPORARY, IFA_F_TENTATIVE,
+    };
+
+    let (connection, handle, _) = rtnetlink::new_connection()?;
+    let conn_task = tokio::spawn(connection);
+
+    let link = handle
+        .link()
+        .get()
+        .match_name(interface.to_string())
+        .execute()
+        .try_next()
+        .await
+        .map_err(|e| format!("Interface {}: {}", interface, e))?
+        .ok_or_else(|| format!("Interface {} not found", interface))?;
+
+    let mut stream = handle
+        .address()
+        .get()
+        .set_link_index_filter(link.header.index)
+        .execute();
+
+    let mut addresses = Vec::new();
+    while let Some(msg) = stream.try_next().await? {
+        let mut flags = msg.header.flags as u32;
+        let mut local = None;
+        let mut address = None;
+        for nla in &msg.nlas {
+            match nla {
+                Nla::Flags(f) => flags = *f,
+                Nla::Local(bytes) => local = Some(bytes.clone()),
+                Nla::Address(bytes) => address = Some(bytes.clone()),
+                _ => {}
+            }
+        }
+        // En enlaces punto a punto (PPPoE) IFA_ADDRESS es el par remoto
+        let Some(bytes) = local.or(address) else {
+            continue;
+        };
+        let ip = match (msg.header.family as u16, bytes.len()) {
+            (AF_INET, 4) => IpAddr::from(<[u8; 4]>::try_from(bytes.as_slice())?),
+            (AF_INET6, 16) => IpAddr::from(<[u8; 16]>::try_from(bytes.as_slice())?),
+            _ => continue,
+        };
+        addresses.push(IfAddress {
+            ip,
+            deprecated: flags & IFA_F_DEPRECATED != 0,
+            tentative: flags & (IFA_F_TENTATIVE | IFA_F_DADFAILED) != 0,
+            // En IPv4 el mismo bit es IFA_F_SECONDARY
+            temporary: ip.is_ipv6() && flags & IFA_F_TEMPORARY != 0,
+        });
+    }
+    conn_task.abort();
+    Ok(addresses)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub async fn list_addresses(interface: &str) -> Result<Vec<IfAddress>, Box<dyn std::error::Error + Send + Sync>> {
+    Err(format!("Reading addresses of {} requires Linux (rtnetlink)", interface).into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn addr(ip: &str, deprecated: bool) -> IfAddress {
+        IfAddress {
+            ip: ip.parse().unwrap(),
+            deprecated,
+            tentative: false,
+            temporary: false,
+        }
+    }
+
+    #[test]
+    fn test_pick_skips_private_link_local_and_deprecated() {
+        let addresses = vec![
+            addr("192.168.1.10", false),
+            addr("100.72.0.1", false),
+            addr("fe80::1", false),
+            addr("2a01:4f8::1", true),
+            addr("2a01:4f8::2", false),
+            addr("81.2.69.142", false),
+        ];
+        assert_eq!(pick_address(&addresses, false), Some("81.2.69.142".parse().unwrap()));
+        assert_eq!(pick_address(&addresses, true), Some("2a01:4f8::2".parse().unwrap()));
+        assert_eq!(pick_address(&addresses[..3], false), None);
+
+        // La temporal de privacidad va primero en la lista del kernel
+        let privacy = IfAddress {
+            temporary: true,
+            ..addr("2a01:4f8::8d3c:1a2b", false)
+        };
+        let addresses = vec![privacy, addr("2a01:4f8::2", false)];
+        assert_eq!(pick_address(&addresses, true), Some("2a01:4f8::2".parse().unwrap()));
+        assert_eq!(pick_address(&addresses[..1], true), Some(privacy.ip));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_list_loopback_addresses() {
+        let addresses = list_addresses("lo").await.unwrap();
+        assert!(addresses.iter().any(|a| a.ip == IpAddr::from([127, 0, 0, 1])));
+    }
+}
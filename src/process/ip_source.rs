@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::future::join_all;
+
+use crate::models::settings::{DnsIpService, IpDetectionSettings, IpSourceConfig};
+
+use super::duck_communicate::{DuckResponse, DuckStatus, send_update_no_ip};
+use super::http_client::http_client;
+use super::iface_ip::interface_ip;
+use super::public_ip::IpResult;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Fuente de la IP pública del host.
+#[async_trait]
+pub trait IpSource: Send + Sync {
+    fn name(&self) -> String;
+    /// Detecta la IP de la familia pedida (`ipv6` o IPv4).
+    async fn detect(&self, ipv6: bool) -> IpResult;
+}
+
+pub struct DnsSource {
+    service: DnsIpService,
+    timeout: Duration,
+}
+
+#[async_trait]
+impl IpSource for DnsSource {
+    fn name(&self) -> String {
+        format!("dns:{}", self.service.name())
+    }
+
+    async fn detect(&self, ipv6: bool) -> IpResult {
+        self.service.lookup_with_timeout(self.timeout, ipv6).await
+    }
+}
+
+/// Servicio estilo ipify: la IP en texto plano o en un campo JSON.
+/// La familia la define la URL (ej. `api6.ipify.org` para IPv6).
+pub struct HttpEchoSource {
+    url: String,
+    json_field: Option<String>,
+    timeout: Duration,
+}
+
+#[async_trait]
+impl IpSource for HttpEchoSource {
+    fn name(&self) -> String {
+        format!("http:{}", self.url)
+    }
+
+    async fn detect(&self, _ipv6: bool) -> IpResult {
+        let body = http_client()
+            .get(&self.url)
+            .timeout(self.timeout)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let text = match &self.json_field {
+            Some(field) => {
+                let json: serde_json::Value = serde_json::from_str(&body)?;
+                json[field.as_str()]
+                    .as_str()
+                    .ok_or_else(|| format!("Field '{}' missing in {}", field, body))?
+                    .to_string()
+            }
+            None => body.trim().to_string(),
+        };
+        Ok(text
+            .parse::<IpAddr>()
+            .map_err(|_| format!("'{}' is not an IP address", text))?)
+    }
+}
+
+/// Deja que DuckDNS detecte la IP actualizando sin `ip=`;
+/// la respuesta verbose trae la dirección que registró.
+pub struct DuckDnsSource {
+    domain: String,
+    token: String,
+}
+
+#[async_trait]
+impl IpSource for DuckDnsSource {
+    fn name(&self) -> String {
+        format!("duckdns:{}", self.domain)
+    }
+
+    async fn detect(&self, ipv6: bool) -> IpResult {
+        let body = send_update_no_ip(&self.domain, &self.token, None, HTTP_TIMEOUT)
+            .await?
+            .text()
+            .await?;
+        let parsed = DuckResponse::parse(&body)?;
+        let ip = match (parsed.status, ipv6) {
+            (DuckStatus::Ko, _) => return Err("DuckDNS answered KO".into()),
+            (_, false) => parsed.ipv4.map(IpAddr::V4),
+            (_, true) => parsed.ipv6.map(IpAddr::V6),
+        };
+        Ok(ip.ok_or_else(|| format!("DuckDNS did not report an address: {}", parsed))?)
+    }
+}
+
+/// Lee la dirección de una interfaz local, sin servicios externos.
+pub struct InterfaceSource {
+    interface: String,
+    ipv6: bool,
+}
+
+#[async_trait]
+impl IpSource for InterfaceSource {
+    fn name(&self) -> String {
+        format!("iface:{}", self.interface)
+    }
+
+    async fn detect(&self, ipv6: bool) -> IpResult {
+        interface_ip(&self.interface, self.ipv6 || ipv6).await
+    }
+}
+
+pub fn build_source(cfg: &IpSourceConfig) -> Box<dyn IpSource> {
+    let ms = |t: &Option<u64>| t.map(Duration::from_millis);
+    match cfg {
+        IpSourceConfig::Dns { service, timeout_ms } => Box::new(DnsSource {
+            service: *service,
+            timeout: ms(timeout_ms).unwrap_or_else(|| service.default_timeout()),
+        }),
+        IpSourceConfig::Http {
+            url,
+            json_field,
+            timeout_ms,
+        } => Box::new(HttpEchoSource {
+            url: url.clone(),
+            json_field: json_field.clone(),
+            timeout: ms(timeout_ms).unwrap_or(HTTP_TIMEOUT),
+        }),
+        IpSourceConfig::Interface { name, ipv6 } => Box::new(InterfaceSource {
+            interface: name.clone(),
+            ipv6: *ipv6,
+        }),
+        IpSourceConfig::DuckDns { domain, token } => Box::new(DuckDnsSource {
+            domain: domain.clone(),
+            token: token.clone(),
+        }),
+    }
+}
+
+/// Resultado de consultar todas las fuentes.
+#[derive(Debug, Default)]
+pub struct Consensus {
+    /// IP aceptada, si se alcanzó el quórum.
+    pub ip: Option<IpAddr>,
+    /// Respuesta de cada fuente que contestó.
+    pub answers: Vec<(String, IpAddr)>,
+    /// Fuentes que fallaron, con el error.
+    pub errors: Vec<(String, String)>,
+}
+
+impl Consensus {
+    /// Hubo al menos dos IPs distintas entre las respuestas.
+    pub fn disagrees(&self) -> bool {
+        self.answers.iter().any(|(_, ip)| *ip != self.answers[0].1)
+    }
+
+    pub fn describe(&self) -> String {
+        self.answers
+            .iter()
+            .map(|(src, ip)| format!("{}={}", src, ip))
+            .chain(self.errors.iter().map(|(src, e)| format!("{}=ERR({})", src, e)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Elige la IP más votada si al menos `quorum` fuentes coinciden.
+/// En empate gana la que aparece primero (orden de `sources`).
+pub fn decide(answers: &[(String, IpAddr)], quorum: usize) -> Option<IpAddr> {
+    let mut votes: HashMap<IpAddr, usize> = HashMap::new();
+    for (_, ip) in answers {
+        *votes.entry(*ip).or_default() += 1;
+    }
+    let mut best: Option<(IpAddr, usize)> = None;
+    for (_, ip) in answers {
+        let n = votes[ip];
+        if best.is_none_or(|(_, b)| n > b) {
+            best = Some((*ip, n));
+        }
+    }
+    best.filter(|(_, n)| *n >= quorum.max(1)).map(|(ip, _)| ip)
+}
+
+/// Consulta todas las fuentes configuradas en paralelo y aplica el quórum.
+/// Una respuesta de la otra familia cuenta como error de esa fuente.
+pub async fn detect_public_ip(cfg: &IpDetectionSettings, ipv6: bool) -> Consensus {
+    let sources: Vec<Box<dyn IpSource>> = cfg.sources.iter().map(build_source).collect();
+    let results = join_all(sources.iter().map(|s| s.detect(ipv6))).await;
+
+    let mut consensus = Consensus::default();
+    for (source, result) in sources.iter().zip(results) {
+        match result {
+            Ok(ip) if ip.is_ipv6() != ipv6 => consensus
+                .errors
+                .push((source.name(), format!("answered {} for the wrong family", ip))),
+            Ok(ip) => consensus.answers.push((source.name(), ip)),
+            Err(e) => consensus.errors.push((source.name(), e.to_string())),
+        }
+    }
+    consensus.ip = decide(&consensus.answers, cfg.quorum);
+    consensus
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn answers(ips: &[&str]) -> Vec<(String, IpAddr)> {
+        ips.iter()
+            .enumerate()
+            .map(|(i, ip)| (format!("src{}", i), ip.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_quorum() {
+        let two_of_three = answers(&["203.0.113.1", "198.51.100.9", "203.0.113.1"]);
+        assert_eq!(decide(&two_of_three, 2), Some("203.0.113.1".parse().unwrap()));
+        assert_eq!(decide(&two_of_three, 3), None);
+
+        let split = answers(&["203.0.113.1", "198.51.100.9"]);
+        assert_eq!(decide(&split, 2), None);
+        assert_eq!(decide(&[], 1), None);
+    }
+}
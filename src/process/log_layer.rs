@@ -0,0 +1,192 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::models::log::Level;
+
+use super::logger::{Event, write_event};
+
+/// Campos que los eventos heredan de sus spans.
+#[derive(Debug, Clone, Default)]
+struct Fields {
+    message: Option<String>,
+    cycle: Option<String>,
+    domain: Option<String>,
+    ip: Option<String>,
+    /// El resto, como "clave=valor"; solo se usan los del evento.
+    extra: Vec<String>,
+}
+
+impl Fields {
+    /// Completa con los de `other`, que tienen prioridad.
+    fn merge(&mut self, other: &Fields) {
+        for (mine, theirs) in [
+            (&mut self.cycle, &other.cycle),
+            (&mut self.domain, &other.domain),
+            (&mut self.ip, &other.ip),
+        ] {
+            if theirs.is_some() {
+                mine.clone_from(theirs);
+            }
+        }
+    }
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.set(field.name(), format!("{:?}", value));
+    }
+}
+
+impl Fields {
+    fn set(&mut self, name: &str, value: String) {
+        match name {
+            "message" => self.message = Some(value),
+            "cycle" => self.cycle = Some(value),
+            "domain" => self.domain = Some(value),
+            "ip" => self.ip = Some(value),
+            _ => self.extra.push(format!("{}={}", name, value)),
+        }
+    }
+}
+
+/// Convierte los eventos de `tracing` en eventos del log, con el ciclo,
+/// dominio e IP de los spans que los contienen, y se los pasa a `emit`.
+pub struct EventLayer<F> {
+    emit: F,
+}
+
+impl<S, F> Layer<S> for EventLayer<F>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    F: Fn(&Event) + 'static,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(fields);
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(fields) = span.extensions_mut().get_mut::<Fields>()
+        {
+            values.record(fields);
+        }
+    }
+
+    fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+        let mut context = Fields::default();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<Fields>() {
+                    context.merge(fields);
+                }
+            }
+        }
+        let mut own = Fields::default();
+        event.record(&mut own);
+        context.merge(&own);
+
+        let level = match *event.metadata().level() {
+            tracing::Level::ERROR => Level::Error,
+            tracing::Level::WARN => Level::Warn,
+            tracing::Level::INFO => Level::Info,
+            _ => Level::Debug,
+        };
+        let mut msg = own.message.unwrap_or_default();
+        for extra in &own.extra {
+            msg.push(' ');
+            msg.push_str(extra);
+        }
+        let mut record = Event::new(level, msg);
+        record.cycle = context.cycle;
+        record.domain = context.domain;
+        record.ip = context.ip;
+        (self.emit)(&record);
+    }
+}
+
+/// Si este proceso es el servicio; lo activa `start`.
+static SERVICE: AtomicBool = AtomicBool::new(false);
+
+/// A partir de acá los eventos van al log de eventos (archivo, journald,
+/// syslog según `settings.log`) en vez de solo a stderr.
+pub fn enable_event_log() {
+    SERVICE.store(true, Ordering::Relaxed);
+}
+
+/// Instala el subscriber global. Los comandos de la CLI solo muestran los
+/// eventos por stderr, hasta que uno pasa a ser el servicio.
+pub fn init() {
+    let layer = EventLayer {
+        emit: |event: &Event| {
+            if SERVICE.load(Ordering::Relaxed) {
+                write_event(event);
+            } else {
+                // Sin `eprintln!`: si stderr se cerró (ej. un pipe) no debe entrar en pánico
+                let _ = writeln!(io::stderr(), "{}", event);
+            }
+        },
+    };
+    // De las dependencias (hyper, reqwest...) solo interesan avisos y errores
+    let ours = filter_fn(|meta| meta.target().starts_with(env!("CARGO_CRATE_NAME")) || *meta.level() <= tracing::Level::WARN);
+    let subscriber = tracing_subscriber::registry().with(layer.with_filter(ours));
+    if let Err(e) = tracing::subscriber::set_global_default(subscriber) {
+        eprintln!("Could not install the log subscriber: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{Instrument, info_span, warn};
+
+    use super::*;
+
+    #[test]
+    fn test_events_inherit_cycle_and_domain_from_spans() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let layer = EventLayer {
+            emit: move |event: &Event| sink.lock().unwrap().push(event.clone()),
+        };
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let cycle = info_span!("cycle", cycle = "ab12cd34");
+            let _cycle = cycle.enter();
+            tracing::info!("Detected IP change");
+            let domain = info_span!("domain", domain = "casa", ip = tracing::field::Empty);
+            domain.record("ip", "203.0.113.5");
+            let update = async { warn!(attempt = 2, "DNS does not resolve yet") }.instrument(domain);
+            futures::executor::block_on(update);
+        });
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].cycle.as_deref(), Some("ab12cd34"));
+        assert_eq!(events[0].domain, None);
+        let e = &events[1];
+        assert_eq!(e.level, Level::Warn);
+        assert_eq!(e.msg, "DNS does not resolve yet attempt=2");
+        assert_eq!(
+            (e.cycle.as_deref(), e.domain.as_deref(), e.ip.as_deref()),
+            (Some("ab12cd34"), Some("casa"), Some("203.0.113.5"))
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone};
+use flate2::read::GzDecoder;
+
+use super::file_watch::watch_file;
+use crate::models::log::Level;
+
+use super::logger::{Event, log_files};
+use super::rutas::events_file;
+
+/// Criterios de `view-log`; los campos en `None` no filtran.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Nivel mínimo.
+    pub level: Option<Level>,
+    pub domain: Option<String>,
+    pub cycle: Option<String>,
+    pub since: Option<DateTime<FixedOffset>>,
+    pub until: Option<DateTime<FixedOffset>>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, event: &Event) -> bool {
+        if self.level.is_some_and(|min| event.level < min) {
+            return false;
+        }
+        if let Some(domain) = &self.domain
+            && !event.domain.as_deref().is_some_and(|d| d.eq_ignore_ascii_case(domain))
+        {
+            return false;
+        }
+        if let Some(cycle) = &self.cycle
+            && event.cycle.as_ref() != Some(cycle)
+        {
+            return false;
+        }
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+        let Ok(ts) = DateTime::parse_from_rfc3339(&event.ts) else {
+            return false;
+        };
+        self.since.is_none_or(|since| ts >= since) && self.until.is_none_or(|until| ts <= until)
+    }
+}
+
+/// Interpreta `--since`/`--until`: relativo ("30m", "2h", "7d"), RFC 3339
+/// o fecha local ("2026-10-13", "2026-10-13 18:30[:00]").
+pub fn parse_time(s: &str, now: DateTime<Local>) -> Result<DateTime<FixedOffset>, String> {
+    let s = s.trim();
+    let invalid = || format!("Invalid time '{}': use 30m, 2h, 7d, 2026-10-13 or 2026-10-13 18:30", s);
+
+    if let Some(unit) = s.chars().last().filter(|c| c.is_ascii_alphabetic())
+        && let Ok(n) = s[..s.len() - 1].parse::<i64>()
+    {
+        let ago = match unit {
+            's' => Duration::seconds(n),
+            'm' => Duration::minutes(n),
+            'h' => Duration::hours(n),
+            'd' => Duration::days(n),
+            _ => return Err(invalid()),
+        };
+        return Ok((now - ago).fixed_offset());
+    }
+    if let Ok(ts) = DateTime::parse_from_rfc3339(s) {
+        return Ok(ts);
+    }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+        .or_else(|| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))
+        .ok_or_else(invalid)?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|t| t.fixed_offset())
+        .ok_or_else(invalid)
+}
+
+/// Eventos que pasan el filtro, del más viejo al más nuevo, leyendo
+/// también los archivos rotados.
+/// Devuelve también hasta dónde se leyó el archivo actual, para que
+/// `follow` siga desde ahí sin perder ni repetir eventos.
+pub fn read_events(filter: &LogFilter) -> io::Result<(Vec<Event>, LogPosition)> {
+    let current = events_file();
+    let mut position = LogPosition::default();
+    let mut events = Vec::new();
+    for path in log_files()? {
+        let read = if path == current {
+            position.read_new(&path)
+        } else {
+            read_log_file(&path)
+        };
+        let content = match read {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        events.extend(parse_lines(&content).filter(|e| filter.matches(e)));
+    }
+    Ok((events, position))
+}
+
+/// Hasta dónde se leyó el archivo actual del log.
+#[derive(Debug, Clone, Default)]
+pub struct LogPosition {
+    inode: u64,
+    offset: u64,
+    /// Resto de una línea que todavía no terminó de escribirse.
+    partial: String,
+}
+
+impl LogPosition {
+    /// Líneas completas agregadas a `path` desde la última lectura. Si el
+    /// archivo rotó, empieza desde el inicio del nuevo.
+    fn read_new(&mut self, path: &Path) -> io::Result<String> {
+        let Ok(mut file) = File::open(path) else {
+            return Ok(String::new());
+        };
+        let meta = file.metadata()?;
+        if meta.ino() != self.inode || meta.len() < self.offset {
+            self.inode = meta.ino();
+            self.offset = 0;
+            self.partial.clear();
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut chunk = String::new();
+        self.offset += file.read_to_string(&mut chunk)? as u64;
+        self.partial.push_str(&chunk);
+        let end = self.partial.rfind('\n').map_or(0, |i| i + 1);
+        Ok(self.partial.drain(..end).collect())
+    }
+}
+
+/// Contenido de un archivo del log; los `.gz` se descomprimen al leer.
+fn read_log_file(path: &Path) -> io::Result<String> {
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        let mut content = String::new();
+        GzDecoder::new(File::open(path)?)
+            .read_to_string(&mut content)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        return Ok(content);
+    }
+    std::fs::read_to_string(path)
+}
+
+/// Las líneas que no son eventos (ej. una escritura cortada) se saltean.
+fn parse_lines(content: &str) -> impl Iterator<Item = Event> + '_ {
+    content.lines().filter_map(|line| serde_json::from_str(line).ok())
+}
+
+/// Sigue el archivo actual desde `position` y llama a `emit` con cada
+/// evento nuevo que pase el filtro. Si el archivo rota, sigue con el nuevo
+/// desde el inicio.
+pub async fn follow(filter: &LogFilter, mut position: LogPosition, mut emit: impl FnMut(&Event)) -> io::Result<()> {
+    let path = events_file();
+    let mut changes = watch_file(&path)?;
+    // Lo escrito entre `read_events` y el watch no genera aviso
+    let mut complete = position.read_new(&path)?;
+    loop {
+        for event in parse_lines(&complete).filter(|e| filter.matches(e)) {
+            emit(&event);
+        }
+        if changes.recv().await.is_none() {
+            return Ok(());
+        }
+        complete = position.read_new(&path)?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_filter_and_time_parsing() {
+        let now = Local.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+        assert_eq!(parse_time("2h", now).unwrap(), (now - Duration::hours(2)).fixed_offset());
+        assert_eq!(
+            parse_time("2026-10-13T08:00:00-03:00", now).unwrap().to_rfc3339(),
+            "2026-10-13T08:00:00-03:00"
+        );
+        assert_eq!(
+            parse_time("2026-10-13", now).unwrap(),
+            Local.with_ymd_and_hms(2026, 10, 13, 0, 0, 0).unwrap().fixed_offset()
+        );
+        assert!(parse_time("el martes", now).is_err());
+
+        let event = |ts: &str, level, domain: Option<&str>| Event {
+            ts: ts.into(),
+            level,
+            cycle: None,
+            domain: domain.map(Into::into),
+            ip: None,
+            msg: "x".into(),
+        };
+        let filter = LogFilter {
+            level: Some(Level::Warn),
+            domain: Some("Casa".into()),
+            cycle: None,
+            since: Some(DateTime::parse_from_rfc3339("2026-10-13T00:00:00-03:00").unwrap()),
+            until: Some(DateTime::parse_from_rfc3339("2026-10-14T00:00:00-03:00").unwrap()),
+        };
+        assert!(filter.matches(&event("2026-10-13T18:30:00.000-03:00", Level::Error, Some("casa"))));
+        assert!(!filter.matches(&event("2026-10-13T18:30:00.000-03:00", Level::Info, Some("casa"))));
+        assert!(!filter.matches(&event("2026-10-13T18:30:00.000-03:00", Level::Warn, Some("oficina"))));
+        assert!(!filter.matches(&event("2026-10-13T18:30:00.000-03:00", Level::Warn, None)));
+        assert!(!filter.matches(&event("2026-10-15T09:00:00.000-03:00", Level::Warn, Some("casa"))));
+    }
+    #[test]
+    fn test_position_keeps_partial_lines_and_rotation() {
+        let path = std::env::temp_dir().join(format!("domainhdlr-follow-{}.jsonl", std::process::id()));
+        std::fs::write(&path, "uno\ndos\ntr").unwrap();
+        let mut position = LogPosition::default();
+        assert_eq!(position.read_new(&path).unwrap(), "uno\ndos\n");
+
+        // Lo que se agrega después de leer no se pierde
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        std::io::Write::write_all(&mut file, b"es\ncuatro\n").unwrap();
+        assert_eq!(position.read_new(&path).unwrap(), "tres\ncuatro\n");
+        assert_eq!(position.read_new(&path).unwrap(), "");
+
+        // Rotado: se empieza del inicio del archivo nuevo
+        std::fs::remove_file(&path).unwrap();
+        std::fs::write(&path, "nuevo\n").unwrap();
+        assert_eq!(position.read_new(&path).unwrap(), "nuevo\n");
+        let _ = std::fs::remove_file(&path);
+    }
+}
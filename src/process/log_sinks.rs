@@ -0,0 +1,355 @@
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::os::unix::net::UnixDatagram;
+use std::sync::mpsc::{SyncSender, TrySendError, sync_channel};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::models::log::Level;
+use crate::models::settings::{SinkConfig, SyslogTransport};
+
+use super::logger::Event;
+
+/// Identificador con el que aparecen las entradas en journald y syslog.
+const APP_NAME: &str = "domainhdlr";
+
+/// SD-ID de los campos estructurados en syslog (número de empresa
+/// reservado para ejemplos, RFC 5612).
+const SD_ID: &str = "domainhdlr@32473";
+
+const NET_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Espera antes de reconectar a syslog tras un fallo; se duplica hasta
+/// `RECONNECT_MAX` mientras siga caído.
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+
+/// Eventos que pueden esperar al hilo de los destinos. Si se llena se
+/// descartan, antes que frenar al daemon.
+const QUEUE_LEN: usize = 1024;
+
+/// Hilo propio que envía a los destinos, así un syslog lento o caído no
+/// bloquea al logger ni al runtime.
+pub struct SinkWriter {
+    tx: SyncSender<Event>,
+    thread: JoinHandle<()>,
+    /// Eventos descartados desde el último aviso.
+    dropped: u64,
+}
+
+impl SinkWriter {
+    pub fn spawn(mut sinks: Vec<Sink>) -> io::Result<Self> {
+        let (tx, rx) = sync_channel::<Event>(QUEUE_LEN);
+        let thread = thread::Builder::new().name("log-sinks".into()).spawn(move || {
+            // Termina cuando se reconfigura el log y se suelta `tx`
+            for event in rx {
+                for sink in &mut sinks {
+                    match sink.send(&event) {
+                        Ok(()) => {}
+                        // Esperando para reconectar: ya se avisó del fallo
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                        Err(e) => eprintln!("Could not send event to log sink: {}", e),
+                    }
+                }
+            }
+        })?;
+        Ok(Self { tx, thread, dropped: 0 })
+    }
+
+    /// Espera a que salga lo encolado; para antes de terminar el proceso.
+    pub fn close(self) {
+        drop(self.tx);
+        let _ = self.thread.join();
+    }
+
+    /// Encola el evento sin esperar.
+    pub fn send(&mut self, event: &Event) {
+        match self.tx.try_send(event.clone()) {
+            Ok(()) if self.dropped > 0 => {
+                eprintln!("Log sinks were behind, {} events dropped", self.dropped);
+                self.dropped = 0;
+            }
+            Ok(()) | Err(TrySendError::Disconnected(_)) => {}
+            Err(TrySendError::Full(_)) => self.dropped += 1,
+        }
+    }
+}
+
+/// Destino adicional al archivo de eventos.
+pub enum Sink {
+    Journald { socket: String, conn: UnixDatagram },
+    Syslog(SyslogSink),
+}
+
+impl Sink {
+    /// Crea el destino; `None` para el archivo, que maneja el logger.
+    pub fn open(cfg: &SinkConfig) -> io::Result<Option<Sink>> {
+        match cfg {
+            SinkConfig::File => Ok(None),
+            SinkConfig::Journald { socket } => Ok(Some(Sink::Journald {
+                socket: socket.clone(),
+                conn: UnixDatagram::unbound()?,
+            })),
+            SinkConfig::Syslog {
+                transport,
+                address,
+                facility,
+            } => Ok(Some(Sink::Syslog(SyslogSink {
+                transport: *transport,
+                address: address.clone(),
+                facility: *facility,
+                hostname: whoami::fallible::hostname().unwrap_or_else(|_| "-".into()),
+                tcp: None,
+                retry_at: None,
+                backoff: RECONNECT_MIN,
+            }))),
+        }
+    }
+
+    pub fn send(&mut self, event: &Event) -> io::Result<()> {
+        match self {
+            Sink::Journald { socket, conn } => conn.send_to(&journal_payload(event), socket.as_str()).map(|_| ()),
+            Sink::Syslog(sink) => sink.send(event),
+        }
+    }
+}
+
+/// Prioridad syslog/journald del nivel.
+fn severity(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug => 7,
+    }
+}
+
+/// Datagrama del protocolo nativo de journald: `CAMPO=valor` por línea, o
+/// el formato binario con largo si el valor tiene saltos de línea.
+fn journal_payload(event: &Event) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut field = |name: &str, value: &str| {
+        out.extend_from_slice(name.as_bytes());
+        if value.contains('\n') {
+            out.push(b'\n');
+            out.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            out.push(b'=');
+        }
+        out.extend_from_slice(value.as_bytes());
+        out.push(b'\n');
+    };
+    field("MESSAGE", &event.msg);
+    field("PRIORITY", &severity(event.level).to_string());
+    field("SYSLOG_IDENTIFIER", APP_NAME);
+    if let Some(cycle) = &event.cycle {
+        field("CYCLE", cycle);
+    }
+    if let Some(domain) = &event.domain {
+        field("DOMAIN", domain);
+    }
+    if let Some(ip) = &event.ip {
+        field("IP", ip);
+    }
+    out
+}
+
+pub struct SyslogSink {
+    transport: SyslogTransport,
+    address: String,
+    facility: u8,
+    hostname: String,
+    /// Conexión TCP abierta; se reabre si se corta.
+    tcp: Option<TcpStream>,
+    /// Tras un fallo de conexión no se reintenta antes de esta hora.
+    retry_at: Option<Instant>,
+    backoff: Duration,
+}
+
+impl SyslogSink {
+    fn send(&mut self, event: &Event) -> io::Result<()> {
+        let msg = self.format(event);
+        match self.transport {
+            SyslogTransport::Udp => {
+                let socket = UdpSocket::bind(if self.address.starts_with('[') { "[::]:0" } else { "0.0.0.0:0" })?;
+                socket.send_to(msg.as_bytes(), self.address.as_str()).map(|_| ())
+            }
+            SyslogTransport::Unix => UnixDatagram::unbound()?.send_to(msg.as_bytes(), &self.address).map(|_| ()),
+            SyslogTransport::Tcp => {
+                // Octet counting (RFC 6587): "<largo> <mensaje>"
+                let frame = format!("{} {}", msg.len(), msg);
+                if let Some(conn) = self.tcp.as_mut()
+                    && conn.write_all(frame.as_bytes()).is_ok()
+                {
+                    return Ok(());
+                }
+                self.tcp = None;
+                if self.retry_at.is_some_and(|at| Instant::now() < at) {
+                    return Err(io::Error::new(io::ErrorKind::WouldBlock, "waiting to reconnect to syslog"));
+                }
+                let result = self.connect().and_then(|mut conn| {
+                    conn.write_all(frame.as_bytes())?;
+                    Ok(conn)
+                });
+                match result {
+                    Ok(conn) => {
+                        self.tcp = Some(conn);
+                        self.retry_at = None;
+                        self.backoff = RECONNECT_MIN;
+                        Ok(())
+                    }
+                    Err(e) => {
+                        self.retry_at = Some(Instant::now() + self.backoff);
+                        let msg = format!("{}: {} (retrying in {:?})", self.address, e, self.backoff);
+                        self.backoff = (self.backoff * 2).min(RECONNECT_MAX);
+                        Err(io::Error::other(msg))
+                    }
+                }
+            }
+        }
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let addr = self
+            .address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Cannot resolve {}", self.address)))?;
+        let conn = TcpStream::connect_timeout(&addr, NET_TIMEOUT)?;
+        conn.set_write_timeout(Some(NET_TIMEOUT))?;
+        Ok(conn)
+    }
+
+    /// Mensaje RFC 5424; dominio e IP van como datos estructurados.
+    fn format(&self, event: &Event) -> String {
+        let pri = self.facility as u32 * 8 + severity(event.level) as u32;
+        let mut params = String::new();
+        if let Some(cycle) = &event.cycle {
+            params.push_str(&format!(" cycle=\"{}\"", sd_escape(cycle)));
+        }
+        if let Some(domain) = &event.domain {
+            params.push_str(&format!(" domain=\"{}\"", sd_escape(domain)));
+        }
+        if let Some(ip) = &event.ip {
+            params.push_str(&format!(" ip=\"{}\"", sd_escape(ip)));
+        }
+        let sd = if params.is_empty() {
+            "-".to_string()
+        } else {
+            format!("[{}{}]", SD_ID, params)
+        };
+        format!(
+            "<{}>1 {} {} {} {} - {} {}",
+            pri,
+            event.ts,
+            self.hostname,
+            APP_NAME,
+            std::process::id(),
+            sd,
+            event.msg
+        )
+    }
+}
+
+fn sd_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace(']', "\\]")
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    use super::*;
+
+    fn event() -> Event {
+        Event {
+            ts: "2026-10-13T18:30:00.000-03:00".into(),
+            level: Level::Warn,
+            cycle: None,
+            domain: Some("casa".into()),
+            ip: Some("203.0.113.5".into()),
+            msg: "DNS desincronizado\nsegunda línea".into(),
+        }
+    }
+
+    #[test]
+    fn test_journald_native_protocol() {
+        let path = std::env::temp_dir().join(format!("domainhdlr-journal-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let journal = UnixDatagram::bind(&path).unwrap();
+
+        let cfg = SinkConfig::Journald {
+            socket: path.to_string_lossy().into(),
+        };
+        Sink::open(&cfg).unwrap().unwrap().send(&event()).unwrap();
+
+        let mut buf = [0u8; 1024];
+        let n = journal.recv(&mut buf).unwrap();
+        let msg = "DNS desincronizado\nsegunda línea";
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&(msg.len() as u64).to_le_bytes());
+        expected.extend_from_slice(msg.as_bytes());
+        expected.extend_from_slice(b"\nPRIORITY=4\nSYSLOG_IDENTIFIER=domainhdlr\nDOMAIN=casa\nIP=203.0.113.5\n");
+        assert_eq!(&buf[..n], expected.as_slice());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_syslog_rfc5424_over_udp_tcp_and_unix() {
+        let open = |transport, address: String| {
+            Sink::open(&SinkConfig::Syslog {
+                transport,
+                address,
+                facility: 3,
+            })
+            .unwrap()
+            .unwrap()
+        };
+        let mut ev = event();
+        ev.msg = "DNS desincronizado".into();
+        let check = |line: &str| {
+            // daemon(3) * 8 + warning(4) = 28
+            assert!(line.starts_with("<28>1 2026-10-13T18:30:00.000-03:00 "), "{}", line);
+            assert!(
+                line.ends_with(&format!(
+                    " domainhdlr {} - [domainhdlr@32473 domain=\"casa\" ip=\"203.0.113.5\"] DNS desincronizado",
+                    std::process::id()
+                )),
+                "{}",
+                line
+            );
+        };
+
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        open(SyslogTransport::Udp, udp.local_addr().unwrap().to_string()).send(&ev).unwrap();
+        let mut buf = [0u8; 1024];
+        let n = udp.recv(&mut buf).unwrap();
+        check(std::str::from_utf8(&buf[..n]).unwrap());
+
+        let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut sink = open(SyslogTransport::Tcp, tcp.local_addr().unwrap().to_string());
+        sink.send(&ev).unwrap();
+        drop(sink);
+        let mut framed = String::new();
+        tcp.accept().unwrap().0.read_to_string(&mut framed).unwrap();
+        let (len, line) = framed.split_once(' ').unwrap();
+        assert_eq!(len.parse::<usize>().unwrap(), line.len());
+        check(line);
+
+        // Caído: un solo intento de conexión, después espera el backoff
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut down = open(SyslogTransport::Tcp, closed.local_addr().unwrap().to_string());
+        drop(closed);
+        assert_ne!(down.send(&ev).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(down.send(&ev).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+
+        let path = std::env::temp_dir().join(format!("domainhdlr-syslog-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let unix = UnixDatagram::bind(&path).unwrap();
+        open(SyslogTransport::Unix, path.to_string_lossy().into()).send(&ev).unwrap();
+        let n = unix.recv(&mut buf).unwrap();
+        check(std::str::from_utf8(&buf[..n]).unwrap());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,143 +1,324 @@
-use std::fs::{self, OpenOptions};
-use std::io::{self, Write};
-use std::path::Path;
-use chrono::Local;
-use super::rutas::{log_file, log_file_error};
-
-/// Escribe una entrada en el log. Si `overwrite` es true, reemplaza todo el contenido.
-/// De lo contrario, inserta la nueva entrada al inicio.
-pub fn entry_for_log(line: &str, overwrite: bool) -> io::Result<()> {
-    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-    let log_entry = format!("[{}] {}\n", timestamp, line);
-
-    let log_path = log_file();
-    let _ = ensure_file_exists(&log_path);
-
-    if overwrite {
-        overwrite_file(log_path.to_str().unwrap(), &log_entry)?;
-    } else {
-        prepend_log_entry(log_path.to_str().unwrap(), &log_entry)?;
-    }
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::models::log::Level;
+use crate::models::settings::{LogSettings, SinkConfig};
+
+use super::log_sinks::{Sink, SinkWriter};
+use super::rutas::{events_file, log_dir};
+
+/// Prefijo de los archivos rotados: `events-<fecha>.jsonl[.gz]`.
+const ARCHIVE_PREFIX: &str = "events-";
 
-    Ok(())
+/// Una línea del log de eventos.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    /// Hora en RFC 3339 con offset.
+    pub ts: String,
+    pub level: Level,
+    /// Ciclo del loop en el que ocurrió.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycle: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    pub msg: String,
 }
-pub fn entry_for_errorlog(line: &str, overwrite: bool) -> io::Result<()> {
-    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-    let log_entry = format!("[{}] {}\n", timestamp, line);
 
-    let log_path = log_file_error();
-    let _ = ensure_file_exists(&log_path);
+impl Event {
+    pub fn new(level: Level, msg: impl Into<String>) -> Self {
+        Self {
+            ts: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+            level,
+            cycle: None,
+            domain: None,
+            ip: None,
+            msg: msg.into(),
+        }
+    }
+}
 
-    if overwrite {
-        overwrite_file(log_path.to_str().unwrap(), &log_entry)?;
-    } else {
-        prepend_log_entry(log_path.to_str().unwrap(), &log_entry)?;
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:<5}", self.ts, self.level)?;
+        if let Some(cycle) = &self.cycle {
+            write!(f, " #{}", cycle)?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, " [{}]", domain)?;
+        }
+        write!(f, " {}", self.msg)?;
+        if let Some(ip) = &self.ip {
+            write!(f, " (ip {})", ip)?;
+        }
+        Ok(())
     }
+}
 
-    Ok(())
+/// Envía el evento a todos los destinos. Un fallo del log se informa
+/// por stderr y no interrumpe a quien lo llama.
+pub fn write_event(event: &Event) {
+    LOG.lock().unwrap().write(event);
 }
 
-/// Crea el archivo si no existe.
-pub fn ensure_file_exists<P: AsRef<Path>>(path: P) -> io::Result<()> {
-    if !path.as_ref().exists() {
-        OpenOptions::new()
-            .create(true)
-            .write(true)
-            .open(path)?;
+/// Aplica los destinos y límites de rotación de `settings.log`.
+pub fn configure(cfg: &LogSettings) {
+    let mut sinks = Vec::new();
+    for sink in &cfg.sinks {
+        match Sink::open(sink) {
+            Ok(Some(sink)) => sinks.push(sink),
+            Ok(None) => {}
+            Err(e) => eprintln!("Could not open log sink {:?}: {}", sink, e),
+        }
     }
-    Ok(())
+    let writer = if sinks.is_empty() {
+        None
+    } else {
+        SinkWriter::spawn(sinks)
+            .inspect_err(|e| eprintln!("Could not start log sink thread: {}", e))
+            .ok()
+    };
+    let mut log = LOG.lock().unwrap();
+    log.cfg = cfg.clone();
+    log.sinks = writer;
 }
 
-/// Sobrescribe el contenido de un archivo con texto nuevo.
-pub fn overwrite_file(path: &str, text: &str) -> io::Result<()> {
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(path)?;
-
-    file.write_all(text.as_bytes())
+/// Termina de enviar lo pendiente a journald/syslog. Los eventos
+/// posteriores solo van al archivo.
+pub fn flush_sinks() {
+    let writer = LOG.lock().unwrap().sinks.take();
+    if let Some(writer) = writer {
+        writer.close();
+    }
 }
 
-/// Inserta la entrada al inicio del archivo.
-pub fn prepend_log_entry(path: &str, new_entry: &str) -> io::Result<()> {
-    // Leer el contenido actual (si hay)
-    let previous = fs::read_to_string(path).unwrap_or_default();
+/// El proceso es el daemon de `start --detached`: su stderr es
+/// `daemon.err.log`.
+pub fn set_detached() {
+    LOG.lock().unwrap().detached = true;
+}
 
-    // Escribir todo de nuevo: nueva entrada + contenido viejo
-    let mut file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(path)?;
+static LOG: Lazy<Mutex<EventLog>> = Lazy::new(|| {
+    Mutex::new(EventLog {
+        current: events_file(),
+        dir: log_dir(),
+        cfg: LogSettings::default(),
+        sinks: None,
+        first_event: None,
+        detached: false,
+    })
+});
 
-    file.write_all(new_entry.as_bytes())?;
-    file.write_all(previous.as_bytes())
+/// Archivo JSON-lines al que solo se agrega; al pasar el tamaño o la
+/// antigüedad configurados se rota a un archivo comprimido. Los demás
+/// destinos (journald, syslog) reciben cada evento además del archivo,
+/// desde su propio hilo.
+struct EventLog {
+    current: PathBuf,
+    dir: PathBuf,
+    cfg: LogSettings,
+    sinks: Option<SinkWriter>,
+    /// Hora del primer evento de `current`, para no releerla en cada evento.
+    first_event: Option<DateTime<FixedOffset>>,
+    detached: bool,
 }
 
+impl EventLog {
+    fn write(&mut self, event: &Event) {
+        if self.echoes(event.level) {
+            // Sin `eprintln!`: si stderr se cerró (ej. un pipe) no debe entrar en pánico
+            let _ = writeln!(io::stderr(), "{}", event);
+        }
+        if self.cfg.sinks.contains(&SinkConfig::File)
+            && let Err(e) = self.append(event)
+        {
+            eprintln!("Could not write event log: {}", e);
+        }
+        if let Some(sinks) = &mut self.sinks {
+            sinks.send(event);
+        }
+    }
 
-#[allow(unused)]
-const MAX_LOG_SIZE: u64 = 25 * 1024; // 25 KB
+    /// Si el evento también va a stderr. Bajo systemd stderr ya llega al
+    /// journal, así que con el destino journald se duplicaría; en segundo
+    /// plano es `daemon.err.log`, que no rota: solo avisos y errores.
+    fn echoes(&self, level: Level) -> bool {
+        let journald = self.cfg.sinks.iter().any(|s| matches!(s, SinkConfig::Journald { .. }));
+        !journald && (!self.detached || level >= Level::Warn)
+    }
+
+    fn append(&mut self, event: &Event) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
 
-#[allow(unused)]
-pub fn purge_log() -> io::Result<()> {
-    let log_path = log_file();
+        if self.should_rotate(line.len() as u64, &event.ts) {
+            self.rotate()?;
+        }
+        self.write_line(&line, &event.ts)
+    }
 
-    // ✅ Crear archivo si no existe
-    if !log_path.exists() {
-        println!("Log file does not exist. Creating it now.");
-        fs::File::create(&log_path)?;
-        return Ok(()); // No hay nada que purgar
+    fn write_line(&mut self, line: &[u8], ts: &str) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.current)?;
+        file.write_all(line)?;
+        if self.first_event.is_none() {
+            self.first_event = DateTime::parse_from_rfc3339(ts).ok();
+        }
+        Ok(())
     }
 
-    // ✅ Intentar leer metadata
-    match fs::metadata(&log_path) {
-        Ok(metadata) => {
-            let file_size = metadata.len();
-            if file_size > MAX_LOG_SIZE {
-                fs::remove_file(&log_path)?; // o renombrar, si preferís rotación
-                println!(
-                    "Log file exceeded size limit ({} bytes). Deleted.",
-                    file_size
-                );
-            } else {
-                println!(
-                    "Log file is within size limit ({} bytes).",
-                    file_size
-                );
+    fn should_rotate(&mut self, incoming: u64, now: &str) -> bool {
+        let size = match fs::metadata(&self.current) {
+            Ok(meta) if meta.len() > 0 => meta.len(),
+            _ => {
+                self.first_event = None;
+                return false;
             }
+        };
+        if size + incoming > self.cfg.max_size_kb * 1024 {
+            return true;
+        }
+        // Solo se lee del archivo si ya existía al arrancar
+        if self.first_event.is_none() {
+            self.first_event = first_event_time(&self.current);
         }
-        Err(e) => {
-            eprintln!(
-                "Could not access log file '{}': {}",
-                log_path.display(),
-                e
+        let (Some(first), Ok(now)) = (self.first_event, DateTime::parse_from_rfc3339(now)) else {
+            return false;
+        };
+        (now - first).num_hours() >= self.cfg.max_age_hours as i64
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let stamp = Local::now().format("%Y%m%d-%H%M%S%3f");
+        let archive = self.dir.join(format!("{}{}.jsonl", ARCHIVE_PREFIX, stamp));
+        fs::rename(&self.current, &archive)?;
+        self.first_event = None;
+        // Sin comprimir el archivo rotado no se pierde; el aviso queda
+        // como primer evento del archivo nuevo
+        if let Err(e) = compress(&archive) {
+            let warning = Event::new(
+                Level::Warn,
+                format!("Could not compress {}, keeping it uncompressed: {}", archive.display(), e),
             );
+            let mut line = serde_json::to_vec(&warning)?;
+            line.push(b'\n');
+            self.write_line(&line, &warning.ts)?;
         }
+        self.prune()
     }
 
-    Ok(())
+    /// Borra los archivos rotados más viejos que excedan `retention`.
+    fn prune(&self) -> io::Result<()> {
+        let archives = archives_in(&self.dir)?;
+        let excess = archives.len().saturating_sub(self.cfg.retention);
+        for old in &archives[..excess] {
+            fs::remove_file(old)?;
+        }
+        Ok(())
+    }
 }
-/// Reads and returns all log entries as a vector of strings.
-/// If the log file does not exist, returns an empty vector.
-#[allow(unused)]
-pub fn read_log_errors() -> io::Result<Vec<String>> {
-    let log_path = log_file();
-    let error_log_path = log_file_error();
-
-    let log_ok = match fs::read_to_string(&log_path) {
-        Ok(ctn) => ctn,
-        _ => String::new()
-    };
 
-    let log_err = match fs::read_to_string(&error_log_path) {
-        Ok(ctn) => ctn,
-        _ => String::new()
+/// Hora del primer evento del archivo.
+fn first_event_time(path: &Path) -> Option<DateTime<FixedOffset>> {
+    let mut line = String::new();
+    BufReader::new(File::open(path).ok()?).read_line(&mut line).ok()?;
+    let event: Event = serde_json::from_str(&line).ok()?;
+    DateTime::parse_from_rfc3339(&event.ts).ok()
+}
+
+/// Comprime `path` a `path.gz` y borra el original.
+fn compress(path: &Path) -> io::Result<()> {
+    let gz = path.with_extension("jsonl.gz");
+    let mut encoder = GzEncoder::new(File::create(&gz)?, Compression::default());
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(path)
+}
+
+/// Archivos rotados en `dir`, del más viejo al más nuevo.
+fn archives_in(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut archives: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            let name = p.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            name.starts_with(ARCHIVE_PREFIX) && (name.ends_with(".jsonl") || name.ends_with(".jsonl.gz"))
+        })
+        .collect();
+    // La fecha en el nombre ordena cronológicamente
+    archives.sort();
+    Ok(archives)
+}
+
+/// Archivos del log, del rotado más viejo al actual.
+pub fn log_files() -> io::Result<Vec<PathBuf>> {
+    let mut files = match archives_in(&log_dir()) {
+        Ok(files) => files,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(e),
     };
+    files.push(events_file());
+    Ok(files)
+}
 
-    Ok(vec![
-        "Log OK : ".to_string(),
-        log_ok,
-        "Log Error: ".to_string(),
-        log_err
-        ])
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_rotation_and_retention() {
+        let dir = std::env::temp_dir().join(format!("domainhdlr-log-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut log = EventLog {
+            current: dir.join("events.jsonl"),
+            dir: dir.clone(),
+            cfg: LogSettings {
+                max_size_kb: 1,
+                max_age_hours: 1,
+                retention: 2,
+                sinks: vec![SinkConfig::File],
+            },
+            sinks: None,
+            first_event: None,
+            detached: false,
+        };
+
+        // Un evento viejo fuerza la rotación por antigüedad
+        let mut old = Event::new(Level::Info, "viejo");
+        old.domain = Some("casa".into());
+        old.ts = "2020-01-01T00:00:00.000-03:00".into();
+        log.append(&old).unwrap();
+        let mut new = Event::new(Level::Warn, "nuevo");
+        new.ip = Some("203.0.113.5".into());
+        log.append(&new).unwrap();
+        let archives = archives_in(&dir).unwrap();
+        assert_eq!(archives.len(), 1);
+        let mut archived = String::new();
+        flate2::read::GzDecoder::new(File::open(&archives[0]).unwrap())
+            .read_to_string(&mut archived)
+            .unwrap();
+        assert!(archived.contains("viejo"));
+        let current = fs::read_to_string(&log.current).unwrap();
+        let event: Event = serde_json::from_str(current.lines().next().unwrap()).unwrap();
+        assert_eq!((event.level, event.ip.as_deref()), (Level::Warn, Some("203.0.113.5")));
+
+        // Por tamaño: varias rotaciones, pero solo quedan `retention`
+        for i in 0..60 {
+            log.append(&Event::new(Level::Error, format!("evento {} con relleno para ocupar lugar", i))).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        assert_eq!(archives_in(&dir).unwrap().len(), 2);
+        assert!(fs::metadata(&log.current).unwrap().len() <= 1024);
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
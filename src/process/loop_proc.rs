@@ -1,241 +1,513 @@
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use futures::future::join_all;
+use futures::stream::{self, StreamExt};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::timeout;
+use tracing::{Instrument, error, info, info_span, warn};
+
 use super::{
-    domains::list_domains,
-    duck_communicate::{get_public_ip, send_update},
-    logger::entry_for_errorlog,
+    domains::{diff_domains, read_domains, set_activated},
+    provider::{Addresses, FailureAction, ProviderResult, UpdateResponse, provider_for},
+    ip_source::detect_public_ip,
+    scheduler::{Scheduler, Wake},
 };
+use crate::models::entry::Entry;
+use crate::models::settings::{AlertKind, Settings};
+use crate::models::state::{DaemonState, DomainState};
 use crate::process::{
-    dns_checker::check_dns_ip,
-    file_lock::get_lock_path,
-    logger::{entry_for_log, purge_log},
-    notifier::{MailConfig, send_email_alert},
+    dns_checker::{Propagation, check_dns},
+    control::{ControlCommand, SharedStatus},
+    logger::configure as configure_log,
+    settings::load_settings,
+    state::{load_state, save_state},
+    notifier::{Alert, notify},
 };
-use chrono::{FixedOffset, Local};
-
-#[allow(unused, deprecated)]
-pub async fn run_loop() {
-    // Configuración del correo
-    let mail_cfg = MailConfig {
-        smtp_server: "smtp.gmail.com".into(),
-        smtp_port: 587,
-        sender: "ptechsoftware.correo@gmail.com".into(),
-        password: "gpoo gqqz cbjq jqzc".into(),
-        recipient: "nachopp98@gmail.com".into(),
+
+/// Ciclos fallidos seguidos tras los cuales un DNS desincronizado se alerta.
+const DNS_MISMATCH_ALERT_AFTER: u32 = 5;
+
+/// Estado que se conserva entre ciclos.
+#[derive(Default)]
+struct LoopState {
+    previous_ip: String,
+    /// Forzar la actualización de todos los dominios (tras un reload).
+    flag: bool,
+    /// Dominios leídos en el último ciclo; `None` antes del primero.
+    known: Option<Vec<Entry>>,
+    /// Dominios agregados o modificados que esperan su actualización.
+    pending: HashSet<String>,
+    last_disagreement: String,
+    last_disagreement_v6: String,
+    /// Ciclos consecutivos con errores; define el backoff.
+    failures: u32,
+    /// Lo que se guarda en `state.json`.
+    saved: DaemonState,
+    domains: usize,
+    active_domains: usize,
+}
+
+/// Entradas que se actualizan juntas, con el historial de cada una.
+type Job<'a> = (Vec<(&'a Entry, Addresses)>, Vec<DomainState>);
+
+/// Por qué terminó `run_loop`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopExit {
+    Stop,
+    Restart,
+}
+
+pub async fn run_loop(
+    settings: &Settings,
+    status: SharedStatus,
+    commands: UnboundedReceiver<ControlCommand>,
+) -> LoopExit {
+    // Copia propia: `reload` la reemplaza
+    let mut settings = settings.clone();
+    let saved = load_state();
+    if !saved.last_ip.is_empty() {
+        info!(ip = %saved.last_ip, "Restored state");
+    }
+    let mut state = LoopState {
+        previous_ip: saved.last_ip.clone(),
+        saved,
+        ..LoopState::default()
     };
+    let mut scheduler = Scheduler::new(&settings, commands);
 
-    let mut previous_ip = String::new();
-    let mut had_previous_errors = false;
-    let mut flag = false;
-    let mut dms = String::new();
+    loop {
+        // Cada ciclo lleva un id para seguirlo en el log de punta a punta
+        let cycle = format!("{:08x}", rand::random::<u32>());
+        let failed = run_cycle(&settings, &mut state)
+            .instrument(info_span!("cycle", cycle = %cycle))
+            .await;
+        state.failures = if failed { state.failures + 1 } else { 0 };
+        publish_status(&settings, &state, &status);
 
-    // Offset horario fijo (-3 Uruguay)
-    let tz_offset = FixedOffset::west(3 * 3600);
+        match scheduler.wait(state.failures).await {
+            Wake::Stop => return LoopExit::Stop,
+            Wake::Restart => return LoopExit::Restart,
+            Wake::Reload(reply) => {
+                let result = load_settings().map_err(|e| e.to_string());
+                match &result {
+                    Ok(new_settings) => {
+                        settings = new_settings.clone();
+                        scheduler.reconfigure(&settings);
+                        // Forzar un ciclo completo con la configuración nueva
+                        state.flag = true;
+                        configure_log(&settings.log);
+                        info!("Settings and domains reloaded");
+                    }
+                    Err(e) => error!("Reload failed, keeping previous settings: {}", e),
+                }
+                if let Some(reply) = reply {
+                    let _ = reply.send(result.map(|_| ()));
+                }
+            }
+            Wake::ConfigChanged => info!("Domains file changed"),
+            Wake::NetworkChange(reason) => info!("Network change detected: {}", reason),
+            Wake::Retry => info!("Retrying updates due to previous errors"),
+            Wake::Timer => {}
+        }
+    }
+}
 
-    let mut exists = get_lock_path().unwrap().exists();
-    while exists == true {
-        // 🔹 Purgar logs viejos
-        let _ = purge_log();
+/// Copia lo relevante del estado del loop a lo que ve `status`.
+fn publish_status(settings: &Settings, state: &LoopState, status: &SharedStatus) {
+    let mut status = status.lock().unwrap();
+    status.public_ip = state.previous_ip.clone();
+    status.last_cycle = Some(settings.now_str());
+    status.failures = state.failures;
+    status.domains = state.domains;
+    status.active_domains = state.active_domains;
+    status.state = state.saved.clone();
+}
 
-        //Listar dominios
-        let domains = list_domains();
-        //Verificar si hubo cambios en los dominios
-        let mut calc = String::new();
-        for el in domains.iter() {
-            calc.push_str(&el.name);
+/// Detecta la IP pública de una familia con sus fuentes y quórum.
+/// Avisa de desacuerdos entre fuentes (una vez por cambio) y de fallos.
+async fn detect_family(settings: &Settings, ipv6: bool, last_disagreement: &mut String) -> Option<IpAddr> {
+    let (family, cfg) = if ipv6 {
+        ("IPv6", &settings.ipv6_detection)
+    } else {
+        ("IPv4", &settings.ip_detection)
+    };
+    let consensus = detect_public_ip(cfg, ipv6).await;
+    if consensus.disagrees() {
+        // Solo se informa cuando cambia el desacuerdo, no en cada vuelta
+        let detail = consensus.describe();
+        if detail != *last_disagreement {
+            warn!("Public {} sources disagree: {}", family, detail);
+            let body = format!(
+                "Las fuentes de {} pública no coinciden.\n{}\nHora: {}",
+                family,
+                detail,
+                settings.now_str()
+            );
+            let _ = notify(settings, Alert::new(AlertKind::IpDisagreement, "⚠️ Fuentes de IP en desacuerdo", body)).await;
+            *last_disagreement = detail;
         }
-        if dms != calc {
-            dms = calc.clone();
-            flag = true;
+    } else {
+        last_disagreement.clear();
+    }
+
+    if consensus.ip.is_none() {
+        let err = format!(
+            "{} of {} sources did not agree: {}",
+            cfg.quorum,
+            cfg.sources.len(),
+            consensus.describe()
+        );
+        let subject = format!("⚠️ Error obteniendo {} pública", family);
+        let body = format!(
+            "No se pudo obtener la {} pública.\nError: {}\nHora: {}",
+            family,
+            err,
+            settings.now_str()
+        );
+        let _ = notify(settings, Alert::new(AlertKind::PublicIpError, subject, body)).await;
+        error!("Could not get public {}: {}", family, err);
+    }
+    consensus.ip
+}
+
+/// Un ciclo completo: detectar IP, actualizar y verificar. Devuelve `true`
+/// si algo falló y hay que reintentar.
+async fn run_cycle(settings: &Settings, state: &mut LoopState) -> bool {
+    //Listar dominios y detectar qué cambió desde el ciclo anterior
+    let domains = match read_domains() {
+        Ok(domains) => domains,
+        Err(e) => {
+            // Un archivo a medio editar no borra los dominios conocidos
+            error!("Invalid domains file, keeping previous domains: {}", e);
+            state.known.clone().unwrap_or_default()
+        }
+    };
+    if let Some(known) = &state.known {
+        for change in diff_domains(known, &domains) {
+            info!("Domains file: {}", change);
+            if let Some(name) = change.needs_update() {
+                state.pending.insert(name.to_string());
+            }
         }
+    }
+    state.known = Some(domains.clone());
 
-        // 🔹 Obtener IP pública
-        match get_public_ip() {
-            Ok(current_ip) => {
-                let ip_changed = current_ip != previous_ip;
+    // 🔹 Obtener IP pública, solo de las familias que algún dominio usa
+    let active: Vec<_> = domains.iter().filter(|d| d.activated).collect();
+    state.domains = domains.len();
+    state.active_domains = active.len();
+    let wants_v4 = active.iter().any(|d| d.ipv4);
+    let wants_v6 = active.iter().any(|d| d.ipv6);
 
-                // Si no cambió la IP ni hubo errores previos ni flag
-                if !ip_changed && !had_previous_errors && !flag {
-                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                    continue;
-                }
+    let mut addrs = Addresses::default();
+    let mut had_errors = false;
+    if wants_v4 {
+        let detect = detect_family(settings, false, &mut state.last_disagreement);
+        match detect.instrument(info_span!("detect_ip", family = "IPv4")).await {
+            Some(IpAddr::V4(ip)) => addrs.ipv4 = Some(ip),
+            _ => had_errors = true,
+        }
+    }
+    if wants_v6 {
+        let detect = detect_family(settings, true, &mut state.last_disagreement_v6);
+        match detect.instrument(info_span!("detect_ip", family = "IPv6")).await {
+            Some(IpAddr::V6(ip)) => addrs.ipv6 = Some(ip),
+            _ => had_errors = true,
+        }
+    }
+    if addrs.is_empty() {
+        return had_errors;
+    }
 
-                if ip_changed {
-                    println!("Detected IP change: {} -> {}", previous_ip, current_ip);
-                    let _ = entry_for_log(
-                        &format!(
-                            "[INFO] Detected IP change: {} -> {}",
-                            previous_ip, current_ip
-                        ),
-                        true,
-                    );
-                    had_previous_errors = false;
-                } else if had_previous_errors {
-                    println!("Retrying due to previous errors");
-                    let _ = entry_for_log("[INFO] Retrying updates due to previous errors", true);
-                }
+    let current_ip = addrs.to_string();
+    let ip_changed = current_ip != state.previous_ip;
+
+    if ip_changed {
+        info!(ip = %current_ip, "Detected IP change: {} -> {}", state.previous_ip, current_ip);
+    }
+
+    state.previous_ip = current_ip.clone();
+    let full = std::mem::take(&mut state.flag);
+    let pending = std::mem::take(&mut state.pending);
+
+    // Se agrupan las entradas que el proveedor puede mandar en un solo
+    // pedido (DuckDNS con el mismo token, TXT e IPs); el resto va sola
+    let mut jobs: Vec<(Option<String>, Job)> = Vec::new();
+    let mut rechecks: Vec<(&Entry, Addresses, DomainState)> = Vec::new();
+    for domain in active {
+        // Un dominio solo IPv6 no se toca si falló la IPv6, y viceversa
+        let wanted = addrs.for_entry(domain);
+        if wanted.is_empty() {
+            continue;
+        }
+        // Solo se actualiza si lo publicado no coincide, cambió su
+        // configuración (aun con el daemon parado) o un reload lo pide
+        let record = state.saved.domains.get(&domain.name).cloned().unwrap_or_default();
+        let up_to_date = record.is_current(&wanted.to_string(), &domain.config_hash());
+        if up_to_date && !full && !pending.contains(&domain.name) {
+            // Aceptado pero sin confirmar en el DNS: se verifica de nuevo
+            // sin volver a empujarlo
+            if record.failures > 0 {
+                rechecks.push((domain, wanted, record));
+            }
+            continue;
+        }
+        let key = provider_for(domain, settings.request_timeout()).batch_key(domain, &wanted);
+        match jobs.iter_mut().find(|(k, _)| key.is_some() && *k == key) {
+            Some((_, (job, records))) => {
+                job.push((domain, wanted));
+                records.push(record);
+            }
+            None => jobs.push((key, (vec![(domain, wanted)], vec![record]))),
+        }
+    }
+    if jobs.is_empty() && rechecks.is_empty() && !ip_changed {
+        return had_errors;
+    }
+
+    // Los trabajos corren en paralelo, hasta `max_concurrent_updates` a la vez
+    let mut outcomes: Vec<(bool, Vec<(String, DomainState)>)> = stream::iter(jobs)
+        .map(|(_, (job, records))| run_job(settings, job, records))
+        .buffer_unordered(settings.max_concurrent_updates.max(1))
+        .collect()
+        .await;
+    let rechecks = rechecks.into_iter().map(|(domain, wanted, mut record)| async move {
+        let span = info_span!("domain", domain = %domain.name, ip = %wanted);
+        let failed = check_published(settings, domain, wanted, &mut record).instrument(span).await;
+        (failed, vec![(domain.name.clone(), record)])
+    });
+    outcomes.extend(join_all(rechecks).await);
+
+    let mut failed = had_errors;
+    for (job_failed, records) in outcomes {
+        failed |= job_failed;
+        state.saved.domains.extend(records);
+    }
+    state.saved.last_ip = current_ip;
+    state.saved.domains.retain(|name, _| domains.iter().any(|d| &d.name == name));
+    if let Err(e) = save_state(&state.saved) {
+        error!("Could not save state: {}", e);
+    }
+    failed
+}
+
+/// Tope para todo un trabajo: cada entrada puede necesitar su pedido al
+/// proveedor y las consultas A y AAAA de la verificación, más el pedido
+/// en lote.
+fn job_timeout(settings: &Settings, entries: usize) -> Duration {
+    let per_entry = settings.request_timeout() + Duration::from_millis(settings.dns_check.timeout_ms) * 2;
+    per_entry * (entries as u32 + 1)
+}
 
-                previous_ip = current_ip.clone();
-                had_previous_errors = false;
-                flag = false;
-                let mut err_ctr = 0;
-
-                for domain in domains.into_iter().filter(|d| d.activated) {
-                    match send_update(&domain.name, &current_ip, &domain.token, domain.txt.clone())
-                        .await
-                    {
-                        Ok(res) => {
-                            let status = &res.status();
-                            let mut respuesta = res.text().await;
-                            match respuesta {
-                                Ok(response) => {
-                                    //Responde OK
-                                    if response.starts_with("OK") {
-                                        println!(
-                                            "Updated {}: {} - {}",
-                                            domain.name, status, response
-                                        );
-                                        let _ = entry_for_log(
-                                            &format!(
-                                                "[SUCCESS] Updated {}: {} | {}",
-                                                domain.name, status, response
-                                            ),
-                                            true,
-                                        );
-                                        had_previous_errors = false;
-                                        err_ctr += 1;
-                                    } else {
-                                        let msg = format!(
-                                            "[ERROR] API give a bad response - {} | {}\n{:?}",
-                                            domain.name,
-                                            Local::now()
-                                                .with_timezone(&tz_offset)
-                                                .format("%Y-%m-%d %H:%M:%S"),
-                                            response
-                                        );
-                                        // ✉️ Enviar alerta por correo
-                                        let subject =
-                                            format!("⚠️ DNS desincronizado para {}", domain.name);
-
-                                        let _ = send_email_alert(&mail_cfg, &subject, &msg).await;
-                                        let _ = entry_for_errorlog(&msg, true);
-                                        had_previous_errors = true;
-                                        err_ctr += 1;
-                                    }
-                                }
-                                Err(bad) => {
-                                    let msg = format!(
-                                        "[ERROR] API give a bad response - {} | {}\n{:?}",
-                                        domain.name,
-                                        Local::now()
-                                            .with_timezone(&tz_offset)
-                                            .format("%Y-%m-%d %H:%M:%S"),
-                                        bad
-                                    );
-                                    // ✉️ Enviar alerta por correo
-                                    let subject =
-                                        format!("⚠️ DNS desincronizado para {}", domain.name);
-
-                                    let _ = send_email_alert(&mail_cfg, &subject, &msg).await;
-                                    let _ = entry_for_errorlog(&msg, true);
-                                    had_previous_errors = true;
-                                    err_ctr += 1;
-                                }
-                            }
-
-                            // 🔹 Verificar resolución DNS
-                            if let Some(dns_ip) = check_dns_ip(&domain.name) {
-                                if dns_ip != current_ip {
-                                    let msg = format!(
-                                        "[WARN] Domain {} still resolves to {} instead of {}",
-                                        domain.name, dns_ip, current_ip
-                                    );
-                                    //Tiempo para que actualice
-                                    tokio::time::sleep(std::time::Duration::from_millis(1000))
-                                        .await;
-                                    println!("{}", msg);
-                                    let _ = entry_for_errorlog(&msg, true);
-
-                                    //si en 5 seg no actualizo mando mail
-                                    if err_ctr > 5 {
-                                        // ✉️ Enviar alerta por correo
-                                        let subject =
-                                            format!("⚠️ DNS desincronizado para {}", domain.name);
-                                        let body = format!(
-                                            "El dominio {} aún apunta a {} en lugar de {}.\nHora: {}",
-                                            domain.name,
-                                            dns_ip,
-                                            current_ip,
-                                            Local::now()
-                                                .with_timezone(&tz_offset)
-                                                .format("%Y-%m-%d %H:%M:%S")
-                                        );
-                                        let _ = send_email_alert(&mail_cfg, &subject, &body).await;
-                                    }
-
-                                    had_previous_errors = true;
-                                    err_ctr += 1;
-                                } else {
-                                    let _ = entry_for_log(
-                                        &format!(
-                                            "[OK] DNS {} resolved correctly to {}",
-                                            domain.name, dns_ip
-                                        ),
-                                        true,
-                                    );
-                                    had_previous_errors = false;
-                                    err_ctr += 1;
-                                }
-                            } else {
-                                let msg = format!(
-                                    "[ERROR] Could not resolve domain {} via nslookup.",
-                                    domain.name
-                                );
-                                println!("{}", msg);
-                                let _ = entry_for_errorlog(&msg, true);
-                                had_previous_errors = true;
-                                err_ctr += 1;
-                            }
-                        }
-                        Err(err) => {
-                            let subject = format!("⚠️ Error actualizando {}", domain.name);
-                            let body = format!(
-                                "No se pudo actualizar el dominio {}.\nError: {}\nHora: {}",
-                                domain.name,
-                                err,
-                                Local::now()
-                                    .with_timezone(&tz_offset)
-                                    .format("%Y-%m-%d %H:%M:%S")
-                            );
-                            let _ = send_email_alert(&mail_cfg, &subject, &body).await;
-
-                            had_previous_errors = true;
-                            let _ = entry_for_errorlog(
-                                &format!("[ERROR] Failed to update {}: {}", domain.name, err),
-                                true,
-                            );
-                            had_previous_errors = true;
-                            err_ctr += 1;
-                        }
+/// Actualiza un grupo de entradas y verifica cada una, con un tope para
+/// todo el trabajo. Devuelve si alguna falló y el historial actualizado
+/// de cada dominio.
+async fn run_job(
+    settings: &Settings,
+    job: Vec<(&Entry, Addresses)>,
+    mut records: Vec<DomainState>,
+) -> (bool, Vec<(String, DomainState)>) {
+    let names: Vec<String> = job.iter().map(|(domain, _)| domain.name.clone()).collect();
+    let limit = job_timeout(settings, job.len());
+    let failed = match timeout(limit, update_job(settings, &job, &mut records)).await {
+        Ok(failed) => failed,
+        Err(_) => {
+            error!("Update of {} timed out after {}s", names.join(", "), limit.as_secs());
+            // Lo que se haya alcanzado a publicar se vuelve a intentar
+            for record in &mut records {
+                record.record_error(format!("Timed out after {}s", limit.as_secs()), settings.now_str());
+            }
+            true
+        }
+    };
+    (failed, names.into_iter().zip(records).collect())
+}
+
+async fn update_job(settings: &Settings, job: &[(&Entry, Addresses)], records: &mut [DomainState]) -> bool {
+    // Cada pedido al proveedor tiene su propio tope (`request_timeout_ms`)
+    let provider = provider_for(job[0].0, settings.request_timeout());
+    let results = provider
+        .update_many(job)
+        .instrument(info_span!("update", provider = provider.name()))
+        .await;
+
+    let checks = job
+        .iter()
+        .zip(results)
+        .zip(records.iter_mut())
+        .map(|(((domain, wanted), result), record)| {
+            let span = info_span!("domain", domain = %domain.name, ip = %wanted);
+            report_domain(settings, provider.name(), domain, *wanted, result, record).instrument(span)
+        });
+    join_all(checks).await.into_iter().any(|e| e)
+}
+
+/// Registra el resultado de actualizar `domain` (también en `record`),
+/// aplica la acción ante fallos y, si el proveedor lo aceptó, verifica
+/// la propagación. Devuelve `true` si algo falló.
+async fn report_domain(
+    settings: &Settings,
+    provider: &str,
+    domain: &Entry,
+    wanted: Addresses,
+    result: ProviderResult<UpdateResponse>,
+    record: &mut DomainState,
+) -> bool {
+    let mut error: Option<String> = None;
+    match result {
+        Ok(response) => {
+            //Responde OK
+            if response.success {
+                info!("Updated via {} | {}", provider, response.detail);
+            } else {
+                let msg = format!(
+                    "[ERROR] API give a bad response - {} | {}\n{:?}",
+                    domain.name,
+                    settings.now_str(),
+                    response.detail
+                );
+                error!("{} gave a bad response: {}", provider, response.detail);
+                match response.on_failure {
+                    FailureAction::Retry => {}
+                    FailureAction::Alert => {
+                        // ✉️ Enviar alerta por correo
+                        let subject =
+                            format!("⚠️ DNS desincronizado para {}", domain.name);
+                        let _ = notify(settings, Alert::new(AlertKind::UpdateError, subject, msg).entry(domain)).await;
+                    }
+                    FailureAction::Disable => {
+                        // Reintentar no sirve: se desactiva hasta que lo revisen
+                        set_activated(&domain.name, false);
+                        let subject =
+                            format!("⛔ Dominio {} desactivado", domain.name);
+                        let body = format!(
+                            "{} rechazó la actualización de {} y el dominio fue desactivado.\nRevise el token o las credenciales y vuelva a activarlo.\nRespuesta: {}\nHora: {}",
+                            provider,
+                            domain.name,
+                            response.detail,
+                            settings.now_str()
+                        );
+                        let _ = notify(settings, Alert::new(AlertKind::EntryDisabled, subject, body).entry(domain)).await;
+                        error!("Domain deactivated");
+                        record.record_error(format!("Rejected, deactivated: {}", response.detail), settings.now_str());
+                        return false;
                     }
                 }
+                error = Some(format!("{} rejected the update: {}", provider, response.detail));
+            }
+        }
+        Err(err) => {
+            let subject = format!("⚠️ Error actualizando {}", domain.name);
+            let body = format!(
+                "No se pudo actualizar el dominio {}.\nError: {}\nHora: {}",
+                domain.name,
+                err,
+                settings.now_str()
+            );
+            let _ = notify(settings, Alert::new(AlertKind::UpdateError, subject, body).entry(domain)).await;
+
+            error!("Failed to update: {}", err);
+            error = Some(err.to_string());
+        }
+    }
 
-                flag = err_ctr > 0;
+    match error {
+        Some(e) => {
+            record.record_error(e, settings.now_str());
+            true
+        }
+        None => check_published(settings, domain, wanted, record).await,
+    }
+}
+
+/// Verifica que el DNS tenga lo que el proveedor aceptó y lo registra en
+/// `record`. Devuelve `true` si no se pudo confirmar.
+async fn check_published(settings: &Settings, domain: &Entry, wanted: Addresses, record: &mut DomainState) -> bool {
+    // Con el proxy de Cloudflare los resolvers devuelven IPs de su
+    // borde; la respuesta de la API ya confirmó el registro
+    let error = if domain.provider.is_proxied() {
+        info!("{} is proxied by Cloudflare, skipping the DNS check", domain.name);
+        None
+    } else {
+        verify_dns(settings, domain, wanted, record).await
+    };
+    match error {
+        Some(e) => {
+            record.record_unverified(wanted.to_string(), domain.config_hash(), e, settings.now_str());
+            true
+        }
+        None => {
+            record.record_success(wanted.to_string(), domain.config_hash(), settings.now_str());
+            false
+        }
+    }
+}
+
+/// Verifica en cada resolver que `domain` resuelva a lo publicado (A y
+/// AAAA). Devuelve el primer problema encontrado.
+async fn verify_dns(settings: &Settings, domain: &Entry, wanted: Addresses, record: &DomainState) -> Option<String> {
+    let mut error: Option<String> = None;
+    let check = check_dns(
+        &domain.fqdn(),
+        wanted.ipv4.is_some(),
+        wanted.ipv6.is_some(),
+        &settings.dns_check,
+        domain.provider.authoritative_server(),
+    )
+    .await;
+    let expected: Vec<IpAddr> = [
+        wanted.ipv4.map(IpAddr::V4),
+        wanted.ipv6.map(IpAddr::V6),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    for ip in expected {
+        match check.propagation(ip) {
+            Propagation::Synced => {
+                info!(ip = %ip, "DNS {} resolved correctly", check.name);
+            }
+            Propagation::CachesStale { ttl } => {
+                // Los caches vencen solos; no es motivo para reintentar
+                info!(
+                    ip = %ip,
+                    "DNS {} update accepted, caches stale for up to {}s | {}",
+                    check.name, ttl, check
+                );
             }
+            Propagation::NotApplied => {
+                warn!(
+                    ip = %ip,
+                    "Domain {} does not resolve to {}: the update did not take | {}",
+                    check.name, ip, check
+                );
 
-            Err(err) => {
-                had_previous_errors = true;
-                let subject = "⚠️ Error obteniendo IP pública".to_string();
-                let body = format!(
-                    "No se pudo obtener la IP pública.\nError: {}\nHora: {}",
-                    err,
-                    Local::now()
-                        .with_timezone(&tz_offset)
-                        .format("%Y-%m-%d %H:%M:%S")
+                // Si sigue desincronizado tras varios reintentos mando mail
+                if record.failures >= DNS_MISMATCH_ALERT_AFTER {
+                    // ✉️ Enviar alerta por correo
+                    let subject = format!("⚠️ DNS desincronizado para {}", domain.name);
+                    let body = format!(
+                        "El dominio {} no apunta a {}.\nRespuestas: {}\nHora: {}",
+                        check.name,
+                        ip,
+                        check,
+                        settings.now_str()
+                    );
+                    let _ = notify(settings, Alert::new(AlertKind::DnsMismatch, subject, body).entry(domain)).await;
+                }
+                error.get_or_insert(format!("DNS does not resolve to {}", ip));
+            }
+            Propagation::Unknown => {
+                error!(
+                    ip = %ip,
+                    "Could not resolve domain {} with any resolver | {}",
+                    check.name, check
                 );
-                let _ = send_email_alert(&mail_cfg, &subject, &body).await;
-                let _ = entry_for_log(&format!("[ERROR] Could not get public IP: {}", err), true);
+                error.get_or_insert("No resolver answered".to_string());
             }
         }
-
-        exists = get_lock_path().unwrap().exists();
     }
+    error
 }
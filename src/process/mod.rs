@@ -6,4 +6,25 @@ pub mod loop_proc;
 pub mod rutas;
 pub mod dns_checker;
 pub mod notifier;
-pub mod file_lock;
\ No newline at end of file
+pub mod file_lock;
+pub mod settings;
+pub mod provider;
+pub mod cloudflare;
+pub mod dyndns2;
+pub mod dns_wire;
+pub mod rfc2136;
+pub mod public_ip;
+pub mod ip_source;
+pub mod iface_ip;
+pub mod scheduler;
+pub mod http_client;
+pub mod control;
+pub mod daemon;
+pub mod file_watch;
+pub mod state;
+pub mod log_query;
+pub mod log_sinks;
+pub mod log_layer;
+pub mod email;
+pub mod webhook;
+pub mod telegram;
\ No newline at end of file
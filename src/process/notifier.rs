@@ -1,51 +1,233 @@
-use lettre::message::{header, Mailbox, Message};
-use lettre::transport::smtp::authentication::Credentials;
-use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
-use std::time::Duration;
-
-/// Configuración del remitente (podés ponerla en tu archivo de config global)
-pub struct MailConfig {
-    pub smtp_server: String, // ejemplo: "smtp.gmail.com"
-    pub smtp_port: u16,      // 587 para STARTTLS
-    pub sender: String,      // correo origen
-    pub password: String,    // clave de aplicación (no tu password real)
-    pub recipient: String,   // correo destino
-}
-
-/// Envía un correo asincrónicamente usando Gmail y TLS.
-/// Devuelve `Ok(())` si el envío fue exitoso.
-pub async fn send_email_alert(
-    cfg: &MailConfig,
-    subject: &str,
-    body: &str,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // 📨 Construir el mensaje
-    let email = Message::builder()
-        .from(Mailbox::new(None, cfg.sender.parse()?))
-        .to(Mailbox::new(None, cfg.recipient.parse()?))
-        .subject(subject)
-        .header(header::ContentType::TEXT_PLAIN)
-        .body(body.to_string())?;
-
-    // 🔐 Autenticación
-    let creds = Credentials::new(cfg.sender.clone(), cfg.password.clone());
-
-    // 🚀 Transport con TLS (STARTTLS en puerto 587)
-    let mailer: AsyncSmtpTransport<Tokio1Executor> = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&cfg.smtp_server)?
-        .port(cfg.smtp_port)
-        .credentials(creds)
-        .timeout(Some(Duration::from_secs(10)))
-        .build();
-
-    // Enviar
-    match mailer.send(email).await {
-        Ok(_) => {
-            println!("📧 Alerta enviada correctamente a {}", cfg.recipient);
-            Ok(())
+use async_trait::async_trait;
+use futures::future::join_all;
+use serde::Serialize;
+use tracing::{error, info, instrument, warn};
+
+use crate::models::entry::Entry;
+use crate::models::log::Level;
+use crate::models::settings::{AlertKind, ChannelConfig, RouteRule, Settings};
+
+use super::email::Email;
+use super::telegram::Telegram;
+use super::webhook::Webhook;
+
+pub type NotifyResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Nombre del canal que sale de `settings.mail`.
+pub const MAIL_CHANNEL: &str = "email";
+
+/// Una alerta lista para enviar por cualquier canal.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub severity: Level,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Alert {
+    pub fn new(kind: AlertKind, subject: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            kind,
+            severity: kind.severity(),
+            domain: None,
+            tags: Vec::new(),
+            subject: subject.into(),
+            body: body.into(),
+        }
+    }
+
+    /// Asocia la alerta al dominio y sus etiquetas.
+    pub fn entry(mut self, entry: &Entry) -> Self {
+        self.domain = Some(entry.name.clone());
+        self.tags = entry.tags.clone();
+        self
+    }
+
+    /// Asunto y cuerpo en un solo texto, para los chats.
+    pub fn text(&self) -> String {
+        format!("{}\n{}", self.subject, self.body)
+    }
+}
+
+/// Un canal por el que salen las alertas.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Nombre con el que lo referencian las rutas.
+    fn name(&self) -> &str;
+
+    async fn send(&self, alert: &Alert) -> NotifyResult<()>;
+}
+
+/// Devuelve el canal configurado.
+pub fn notifier_for(cfg: &ChannelConfig) -> Box<dyn Notifier> {
+    match cfg {
+        ChannelConfig::Email { name, mail } => Box::new(Email::new(name, mail)),
+        ChannelConfig::Webhook { name, url, format } => Box::new(Webhook::new(name, url, *format)),
+        ChannelConfig::Telegram {
+            name,
+            bot_token,
+            chat_id,
+            api_base,
+        } => Box::new(Telegram::new(name, bot_token, chat_id, api_base.as_deref())),
+    }
+}
+
+/// Si `alert` cumple todos los criterios no vacíos de la regla.
+fn route_matches(rule: &RouteRule, alert: &Alert) -> bool {
+    let domain = alert.domain.as_deref();
+    (rule.kinds.is_empty() || rule.kinds.contains(&alert.kind))
+        && rule.min_severity.is_none_or(|min| alert.severity >= min)
+        && (rule.domains.is_empty() || domain.is_some_and(|d| rule.domains.iter().any(|r| r.eq_ignore_ascii_case(d))))
+        && (rule.tags.is_empty() || alert.tags.iter().any(|t| rule.tags.contains(t)))
+}
+
+/// Reparte cada alerta entre los canales que le tocan según las rutas.
+pub struct Dispatcher {
+    channels: Vec<Box<dyn Notifier>>,
+    routes: Vec<RouteRule>,
+}
+
+impl Dispatcher {
+    pub fn new(channels: Vec<Box<dyn Notifier>>, routes: Vec<RouteRule>) -> Self {
+        Self { channels, routes }
+    }
+
+    /// El correo de `settings.mail` más los canales de `notifications`.
+    pub fn from_settings(settings: &Settings) -> Self {
+        let mut channels: Vec<Box<dyn Notifier>> = Vec::new();
+        if let Some(mail) = &settings.mail {
+            channels.push(Box::new(Email::new(MAIL_CHANNEL, mail)));
         }
-        Err(e) => {
-            eprintln!("❌ Error al enviar correo: {}", e);
-            Err(Box::new(e))
+        channels.extend(settings.notifications.channels.iter().map(notifier_for));
+        Self::new(channels, settings.notifications.routes.clone())
+    }
+
+    /// Canales que deben recibir `alert`, en el orden en que se configuraron.
+    pub fn targets(&self, alert: &Alert) -> Vec<&dyn Notifier> {
+        if self.routes.is_empty() {
+            return self.channels.iter().map(|c| c.as_ref()).collect();
+        }
+        let wanted: Vec<&String> = self
+            .routes
+            .iter()
+            .filter(|rule| route_matches(rule, alert))
+            .flat_map(|rule| &rule.channels)
+            .collect();
+        for name in &wanted {
+            if !self.channels.iter().any(|c| c.name() == name.as_str()) {
+                warn!("Notification route points to unknown channel '{}'", name);
+            }
+        }
+        self.channels
+            .iter()
+            .filter(|c| wanted.iter().any(|name| c.name() == name.as_str()))
+            .map(|c| c.as_ref())
+            .collect()
+    }
+
+    /// Envía a todos los destinos a la vez. Un canal caído no frena a los
+    /// demás; devuelve el primer error.
+    #[instrument(name = "notify", skip_all, fields(kind = ?alert.kind))]
+    pub async fn dispatch(&self, alert: &Alert) -> NotifyResult<()> {
+        let targets = self.targets(alert);
+        let sends = targets.iter().map(|channel| channel.send(alert));
+        let mut first_error = None;
+        for (channel, result) in targets.iter().zip(join_all(sends).await) {
+            match result {
+                Ok(()) => info!("Alert sent via {}", channel.name()),
+                Err(e) => {
+                    error!("Could not send alert via {}: {}", channel.name(), e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+/// Envía la alerta si los settings la habilitan, por los canales que
+/// correspondan según las rutas.
+pub async fn notify(settings: &Settings, alert: Alert) -> NotifyResult<()> {
+    let n = &settings.notifications;
+    if !n.enabled || !n.allows(alert.kind) {
+        return Ok(());
+    }
+    Dispatcher::from_settings(settings).dispatch(&alert).await
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    type Sent = Arc<Mutex<Vec<(String, AlertKind)>>>;
+
+    struct Recorder {
+        name: String,
+        sent: Sent,
+    }
+
+    #[async_trait]
+    impl Notifier for Recorder {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        async fn send(&self, alert: &Alert) -> NotifyResult<()> {
+            self.sent.lock().unwrap().push((self.name.clone(), alert.kind));
+            Ok(())
         }
     }
+
+    fn alert(kind: AlertKind, tags: &[&str]) -> Alert {
+        let mut alert = Alert::new(kind, "asunto", "cuerpo");
+        alert.domain = Some("casa".into());
+        alert.tags = tags.iter().map(|t| t.to_string()).collect();
+        alert
+    }
+
+    #[tokio::test]
+    async fn test_routes_by_kind_severity_and_tag() {
+        // "token inválido → correo y chat, DNS demorado → solo chat"
+        let routes: Vec<RouteRule> = serde_json::from_str(
+            r#"[
+                {"kinds": ["entry_disabled"], "channels": ["email", "chat"]},
+                {"kinds": ["dns_mismatch"], "channels": ["chat"]},
+                {"min_severity": "error", "tags": ["prod"], "channels": ["pager", "falta"]}
+            ]"#,
+        )
+        .unwrap();
+        let sent = Sent::default();
+        let channels = ["email", "chat", "pager"]
+            .iter()
+            .map(|name| {
+                Box::new(Recorder {
+                    name: name.to_string(),
+                    sent: sent.clone(),
+                }) as Box<dyn Notifier>
+            })
+            .collect();
+        let dispatcher = Dispatcher::new(channels, routes);
+        let targets = |alert: &Alert| -> Vec<String> {
+            dispatcher.targets(alert).iter().map(|c| c.name().to_string()).collect()
+        };
+
+        assert_eq!(targets(&alert(AlertKind::EntryDisabled, &[])), ["email", "chat"]);
+        assert_eq!(targets(&alert(AlertKind::EntryDisabled, &["prod"])), ["email", "chat", "pager"]);
+        assert_eq!(targets(&alert(AlertKind::DnsMismatch, &["prod"])), ["chat"]);
+        assert!(targets(&alert(AlertKind::UpdateError, &[])).is_empty());
+
+        dispatcher.dispatch(&alert(AlertKind::DnsMismatch, &[])).await.unwrap();
+        assert_eq!(*sent.lock().unwrap(), [("chat".to_string(), AlertKind::DnsMismatch)]);
+
+        // Sin rutas va a todos los canales
+        let dispatcher = Dispatcher::new(dispatcher.channels, Vec::new());
+        assert_eq!(dispatcher.targets(&alert(AlertKind::ServiceStopping, &[])).len(), 3);
+    }
 }
@@ -0,0 +1,148 @@
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::models::entry::{Entry, ProviderConfig};
+
+use super::cloudflare::Cloudflare;
+use super::duck_communicate::DuckDns;
+use super::dyndns2::DynDns2;
+use super::rfc2136::Rfc2136;
+
+pub type ProviderResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Qué debe hacer `run_loop` cuando el proveedor rechaza una actualización.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FailureAction {
+    /// Error transitorio: se registra y se reintenta sin alertar.
+    Retry,
+    /// Requiere atención: se alerta y se reintenta.
+    #[default]
+    Alert,
+    /// Error permanente (credenciales, host inexistente, abuso):
+    /// se alerta y se desactiva la entrada.
+    Disable,
+}
+
+/// Resultado de una llamada al proveedor, ya interpretado.
+#[derive(Debug, Clone)]
+pub struct UpdateResponse {
+    pub success: bool,
+    /// Respuesta cruda o mensaje del proveedor, para logs y alertas.
+    pub detail: String,
+    /// Solo relevante si `success` es false.
+    pub on_failure: FailureAction,
+}
+
+impl UpdateResponse {
+    pub fn ok(detail: impl Into<String>) -> Self {
+        Self {
+            success: true,
+            detail: detail.into(),
+            on_failure: FailureAction::default(),
+        }
+    }
+
+    pub fn failed(detail: impl Into<String>, on_failure: FailureAction) -> Self {
+        Self {
+            success: false,
+            detail: detail.into(),
+            on_failure,
+        }
+    }
+}
+
+/// Direcciones a publicar; cada familia es opcional.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Addresses {
+    pub ipv4: Option<Ipv4Addr>,
+    pub ipv6: Option<Ipv6Addr>,
+}
+
+impl Addresses {
+    /// Solo las familias que la entrada tiene habilitadas.
+    pub fn for_entry(&self, entry: &Entry) -> Addresses {
+        Addresses {
+            ipv4: self.ipv4.filter(|_| entry.ipv4),
+            ipv6: self.ipv6.filter(|_| entry.ipv6),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ipv4.is_none() && self.ipv6.is_none()
+    }
+}
+
+impl fmt::Display for Addresses {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v4 = self.ipv4.map(|ip| ip.to_string());
+        let v6 = self.ipv6.map(|ip| ip.to_string());
+        let parts: Vec<String> = [v4, v6].into_iter().flatten().collect();
+        if parts.is_empty() {
+            write!(f, "-")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
+
+/// Backend capaz de actualizar los registros de un dominio.
+#[async_trait]
+pub trait DnsProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Publica las direcciones presentes en `addrs` (A y/o AAAA) y,
+    /// si la entrada tiene, su TXT.
+    async fn update(&self, entry: &Entry, addrs: &Addresses) -> ProviderResult<UpdateResponse>;
+
+    /// Las entradas con la misma clave pueden ir juntas en una sola
+    /// llamada de `update_many`; `None` si la entrada va sola.
+    fn batch_key(&self, _entry: &Entry, _addrs: &Addresses) -> Option<String> {
+        None
+    }
+
+    /// Actualiza varias entradas que comparten esta configuración y
+    /// devuelve un resultado por entrada, en el mismo orden. Por defecto
+    /// va una por una; los proveedores que aceptan lotes lo sobrescriben.
+    async fn update_many(&self, entries: &[(&Entry, Addresses)]) -> Vec<ProviderResult<UpdateResponse>> {
+        let mut results = Vec::with_capacity(entries.len());
+        for (entry, addrs) in entries {
+            results.push(self.update(entry, addrs).await);
+        }
+        results
+    }
+
+    async fn set_txt(&self, entry: &Entry, value: &str) -> ProviderResult<UpdateResponse>;
+
+    async fn clear_txt(&self, entry: &Entry) -> ProviderResult<UpdateResponse>;
+}
+
+/// Devuelve el backend configurado para la entrada; cada pedido al
+/// proveedor se corta a los `timeout`.
+pub fn provider_for(entry: &Entry, timeout: Duration) -> Box<dyn DnsProvider> {
+    match &entry.provider {
+        ProviderConfig::DuckDns { .. } => Box::new(DuckDns::new(timeout)),
+        ProviderConfig::Cloudflare {
+            api_token,
+            zone,
+            proxied,
+            ttl,
+            api_base,
+        } => Box::new(Cloudflare::new(api_token, zone, *proxied, *ttl, api_base.as_deref(), timeout)),
+        ProviderConfig::DynDns2 {
+            server,
+            username,
+            password,
+        } => Box::new(DynDns2::new(server, username, password, timeout)),
+        ProviderConfig::Rfc2136 {
+            server,
+            zone,
+            key_name,
+            key_secret,
+            algorithm,
+            ttl,
+        } => Box::new(Rfc2136::new(server, zone, key_name, key_secret, algorithm, *ttl, timeout)),
+    }
+}
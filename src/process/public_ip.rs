@@ -0,0 +1,127 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use hickory_proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::{DNSClass, Name, RData, RecordType};
+
+use crate::models::settings::DnsIpService;
+
+use super::dns_wire::exchange;
+
+pub type IpResult = Result<IpAddr, Box<dyn std::error::Error + Send + Sync>>;
+
+impl DnsIpService {
+    /// El servidor ve la IP de origen de la consulta, así que para
+    /// conocer la IPv6 hay que consultarlo por IPv6.
+    fn server(&self, ipv6: bool) -> SocketAddr {
+        let ip = match (self, ipv6) {
+            (DnsIpService::OpenDns, false) => "208.67.222.222",
+            (DnsIpService::OpenDns, true) => "2620:119:35::35",
+            (DnsIpService::Google, false) => "216.239.32.10",
+            (DnsIpService::Google, true) => "2001:4860:4802:32::a",
+            (DnsIpService::Cloudflare, false) => "1.1.1.1",
+            (DnsIpService::Cloudflare, true) => "2606:4700:4700::1111",
+        };
+        SocketAddr::new(ip.parse().unwrap(), 53)
+    }
+
+    fn query(&self, ipv6: bool) -> Query {
+        let opendns_kind = if ipv6 { RecordType::AAAA } else { RecordType::A };
+        let (name, kind, class) = match self {
+            DnsIpService::OpenDns => ("myip.opendns.com.", opendns_kind, DNSClass::IN),
+            DnsIpService::Google => ("o-o.myaddr.l.google.com.", RecordType::TXT, DNSClass::IN),
+            DnsIpService::Cloudflare => ("whoami.cloudflare.", RecordType::TXT, DNSClass::CH),
+        };
+        let mut query = Query::query(Name::from_ascii(name).unwrap(), kind);
+        query.set_query_class(class);
+        query
+    }
+
+    pub async fn lookup_with_timeout(&self, timeout: Duration, ipv6: bool) -> IpResult {
+        let mut msg = Message::new();
+        msg.set_id(rand::random())
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(false);
+        msg.add_query(self.query(ipv6));
+
+        let raw = exchange(self.server(ipv6), &msg.to_vec()?, timeout).await?;
+        parse_answer(&Message::from_vec(&raw)?)
+            .map_err(|e| format!("{}: {}", self.name(), e).into())
+    }
+}
+
+/// Extrae una única IP de la respuesta. Falla si no hay ninguna,
+/// si alguna no es una IP válida o si hay IPs distintas.
+fn parse_answer(response: &Message) -> Result<IpAddr, String> {
+    if response.response_code() != ResponseCode::NoError {
+        return Err(format!("server answered {}", response.response_code()));
+    }
+
+    let mut found: Option<IpAddr> = None;
+    for record in response.answers() {
+        let ip = match record.data() {
+            RData::A(a) => IpAddr::V4(a.0),
+            RData::AAAA(aaaa) => IpAddr::V6(aaaa.0),
+            RData::TXT(txt) => {
+                let [text] = txt.txt_data() else {
+                    return Err("unexpected multi-string TXT answer".into());
+                };
+                let text = std::str::from_utf8(text).map_err(|_| "TXT answer is not UTF-8")?;
+                // Google agrega un TXT "edns0-client-subnet ..." que no es la IP del cliente
+                if text.starts_with("edns0-client-subnet") {
+                    continue;
+                }
+                text.parse::<IpAddr>()
+                    .map_err(|_| format!("TXT answer '{}' is not an IP address", text))?
+            }
+            _ => continue,
+        };
+        match found {
+            Some(prev) if prev != ip => {
+                return Err(format!("conflicting answers {} and {}", prev, ip));
+            }
+            _ => found = Some(ip),
+        }
+    }
+    found.ok_or_else(|| "no address in answer".to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hickory_proto::rr::Record;
+    use hickory_proto::rr::rdata::{A, TXT};
+
+    fn response(records: Vec<RData>) -> Message {
+        let mut msg = Message::new();
+        msg.set_message_type(MessageType::Response);
+        for data in records {
+            msg.add_answer(Record::from_rdata(Name::root(), 0, data));
+        }
+        msg
+    }
+
+    #[test]
+    fn test_parse_answer_is_strict() {
+        let a = response(vec![RData::A(A("203.0.113.9".parse().unwrap()))]);
+        assert_eq!(parse_answer(&a).unwrap(), "203.0.113.9".parse::<IpAddr>().unwrap());
+
+        let google = response(vec![
+            RData::TXT(TXT::new(vec!["2001:db8::1".into()])),
+            RData::TXT(TXT::new(vec!["edns0-client-subnet 198.51.100.0/24".into()])),
+        ]);
+        assert_eq!(parse_answer(&google).unwrap(), "2001:db8::1".parse::<IpAddr>().unwrap());
+
+        let garbage = response(vec![RData::TXT(TXT::new(vec![";; WARNING: recursion".into()]))]);
+        assert!(parse_answer(&garbage).is_err());
+
+        let conflict = response(vec![
+            RData::A(A("203.0.113.9".parse().unwrap())),
+            RData::A(A("203.0.113.10".parse().unwrap())),
+        ]);
+        assert!(parse_answer(&conflict).is_err());
+
+        assert!(parse_answer(&response(vec![])).is_err());
+    }
+}
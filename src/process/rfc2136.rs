@@ -0,0 +1,353 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use base64::Engine;
+use hickory_proto::dnssec::rdata::tsig::TsigAlgorithm;
+use hickory_proto::dnssec::tsig::TSigner;
+use hickory_proto::op::{Message, MessageType, OpCode, Query, ResponseCode, UpdateMessage};
+use hickory_proto::rr::rdata::{A, AAAA, TXT};
+use hickory_proto::rr::{DNSClass, Name, RData, Record, RecordType};
+
+use crate::models::entry::Entry;
+use crate::process::dns_wire::{exchange, resolve_server};
+use crate::process::provider::{Addresses, DnsProvider, FailureAction, ProviderResult, UpdateResponse};
+
+/// Diferencia de reloj tolerada con el servidor, en segundos.
+const TSIG_FUDGE: u16 = 300;
+
+/// Actualizaciones dinámicas RFC 2136 firmadas con TSIG, para servidores
+/// propios (BIND, Knot, PowerDNS). Reemplaza el RRset completo.
+pub struct Rfc2136 {
+    server: String,
+    zone: String,
+    key_name: String,
+    key_secret: String,
+    algorithm: String,
+    ttl: u32,
+    timeout: Duration,
+}
+
+impl Rfc2136 {
+    /// `key_secret` va en base64, como en `tsig-keygen`.
+    pub fn new(
+        server: &str,
+        zone: &str,
+        key_name: &str,
+        key_secret: &str,
+        algorithm: &str,
+        ttl: u32,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            server: server.to_string(),
+            zone: zone.to_string(),
+            key_name: key_name.to_string(),
+            key_secret: key_secret.to_string(),
+            algorithm: algorithm.to_string(),
+            ttl,
+            timeout,
+        }
+    }
+
+    fn signer(&self) -> ProviderResult<TSigner> {
+        let algorithm = match self.algorithm.to_ascii_lowercase().as_str() {
+            "hmac-sha256" => TsigAlgorithm::HmacSha256,
+            "hmac-sha384" => TsigAlgorithm::HmacSha384,
+            "hmac-sha512" => TsigAlgorithm::HmacSha512,
+            other => return Err(format!("Unsupported TSIG algorithm '{}'", other).into()),
+        };
+        let key = base64::engine::general_purpose::STANDARD
+            .decode(self.key_secret.trim())
+            .map_err(|e| format!("Invalid TSIG secret: {}", e))?;
+        Ok(TSigner::new(key, algorithm, Name::from_ascii(&self.key_name)?, TSIG_FUDGE)?)
+    }
+
+    fn fqdn(name: &str) -> ProviderResult<Name> {
+        let mut name = Name::from_ascii(name)?;
+        name.set_fqdn(true);
+        Ok(name)
+    }
+
+    /// Arma, firma y envía un UPDATE con los registros dados.
+    async fn send_update(&self, updates: Vec<Record>) -> ProviderResult<Option<UpdateResponse>> {
+        let mut zone = Query::new();
+        zone.set_name(Self::fqdn(&self.zone)?)
+            .set_query_class(DNSClass::IN)
+            .set_query_type(RecordType::SOA);
+
+        let mut msg = Message::new();
+        msg.set_id(rand::random())
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Update)
+            .set_recursion_desired(false);
+        msg.add_zone(zone);
+        for record in updates {
+            msg.add_update(record);
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as u32;
+        let verifier = msg.finalize(&self.signer()?, now)?;
+        let server = resolve_server(&self.server, 53).await?;
+        let raw = exchange(server, &msg.to_vec()?, self.timeout).await?;
+
+        // Los errores de TSIG (BADKEY/BADSIG) pueden venir sin firmar.
+        let response = Message::from_vec(&raw)?;
+        let parsed = classify(response.response_code());
+        if !parsed.success {
+            return Ok(Some(UpdateResponse::failed(
+                format!("{} rejected the update: {}", self.server, parsed.detail),
+                parsed.on_failure,
+            )));
+        }
+        if let Some(mut verify) = verifier
+            && let Err(e) = verify(&raw)
+        {
+            return Ok(Some(UpdateResponse::failed(
+                format!("Invalid TSIG on response from {}: {}", self.server, e),
+                FailureAction::Alert,
+            )));
+        }
+        Ok(None)
+    }
+
+    /// Consulta directa (sin recursión) al servidor del que se actualizó.
+    async fn query(&self, name: &Name, kind: RecordType) -> ProviderResult<Vec<Record>> {
+        let mut msg = Message::new();
+        msg.set_id(rand::random())
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(false);
+        msg.add_query(Query::query(name.clone(), kind));
+
+        let server = resolve_server(&self.server, 53).await?;
+        let raw = exchange(server, &msg.to_vec()?, self.timeout).await?;
+        Ok(Message::from_vec(&raw)?.answers().to_vec())
+    }
+
+    async fn zone_serial(&self) -> Option<u32> {
+        let zone = Self::fqdn(&self.zone).ok()?;
+        self.query(&zone, RecordType::SOA)
+            .await
+            .ok()?
+            .iter()
+            .find_map(|r| match r.data() {
+                RData::SOA(soa) => Some(soa.serial()),
+                _ => None,
+            })
+    }
+
+    /// Borra cada RRset y lo vuelve a crear con su dato, en un único
+    /// UPDATE; luego verifica consultando al servidor.
+    async fn replace_rrsets(&self, name: &str, data: Vec<RData>) -> ProviderResult<UpdateResponse> {
+        let name = Self::fqdn(name)?;
+
+        let mut updates = Vec::new();
+        for rdata in &data {
+            let mut delete = Record::update0(name.clone(), 0, rdata.record_type());
+            delete.set_dns_class(DNSClass::ANY);
+            updates.push(delete);
+            updates.push(Record::from_rdata(name.clone(), self.ttl, rdata.clone()));
+        }
+        if let Some(failed) = self.send_update(updates).await? {
+            return Ok(failed);
+        }
+
+        let mut details = Vec::new();
+        for rdata in data {
+            let kind = rdata.record_type();
+            let answers = self.query(&name, kind).await?;
+            if !answers.iter().any(|r| r.data() == &rdata) {
+                return Ok(UpdateResponse::failed(
+                    format!("{} accepted the update but {} {} is not {}", self.server, kind, name, rdata),
+                    FailureAction::Alert,
+                ));
+            }
+            details.push(format!("{} {} -> {}", kind, name, rdata));
+        }
+        let serial = self
+            .zone_serial()
+            .await
+            .map(|s| format!(" (serial {})", s))
+            .unwrap_or_default();
+        Ok(UpdateResponse::ok(format!("{}{}", details.join(" | "), serial)))
+    }
+}
+
+#[async_trait]
+impl DnsProvider for Rfc2136 {
+    fn name(&self) -> &'static str {
+        "rfc2136"
+    }
+
+    async fn update(&self, entry: &Entry, addrs: &Addresses) -> ProviderResult<UpdateResponse> {
+        let data: Vec<RData> = [
+            addrs.ipv4.map(|v4| RData::A(A(v4))),
+            addrs.ipv6.map(|v6| RData::AAAA(AAAA(v6))),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let mut res = self.replace_rrsets(&entry.name, data).await?;
+        if let (true, Some(txt)) = (res.success, &entry.txt) {
+            let txt_res = self.set_txt(entry, txt).await?;
+            res.detail = format!("{} | {}", res.detail, txt_res.detail);
+            res.success = txt_res.success;
+            res.on_failure = txt_res.on_failure;
+        }
+        Ok(res)
+    }
+
+    async fn set_txt(&self, entry: &Entry, value: &str) -> ProviderResult<UpdateResponse> {
+        self.replace_rrsets(&entry.name, vec![RData::TXT(TXT::new(vec![value.to_string()]))])
+            .await
+    }
+
+    async fn clear_txt(&self, entry: &Entry) -> ProviderResult<UpdateResponse> {
+        let name = Self::fqdn(&entry.name)?;
+        let mut delete = Record::update0(name.clone(), 0, RecordType::TXT);
+        delete.set_dns_class(DNSClass::ANY);
+        match self.send_update(vec![delete]).await? {
+            Some(failed) => Ok(failed),
+            None => Ok(UpdateResponse::ok(format!("TXT {} deleted", name))),
+        }
+    }
+}
+
+/// Qué hacer según el RCODE de la respuesta a un UPDATE.
+fn classify(rcode: ResponseCode) -> UpdateResponse {
+    match rcode {
+        ResponseCode::NoError => UpdateResponse::ok(rcode.to_string()),
+        ResponseCode::ServFail => UpdateResponse::failed(rcode.to_string(), FailureAction::Retry),
+        ResponseCode::NotAuth | ResponseCode::Refused | ResponseCode::NotZone => {
+            UpdateResponse::failed(rcode.to_string(), FailureAction::Disable)
+        }
+        _ => UpdateResponse::failed(rcode.to_string(), FailureAction::Alert),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::entry::ProviderConfig;
+    use hickory_proto::dnssec::rdata::tsig::{TSIG, make_tsig_record, message_tbs};
+    use std::sync::{Arc, Mutex};
+    use tokio::net::UdpSocket;
+
+    const SECRET: &str = "c2VjcmV0LWtleS1mb3ItdGVzdHMtb25seS0xMjM0NTY=";
+
+    /// Servidor DNS mínimo: aplica UPDATEs firmados y responde consultas.
+    async fn spawn_server(records: Arc<Mutex<Vec<Record>>>) -> String {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+        let key = base64::engine::general_purpose::STANDARD.decode(SECRET).unwrap();
+        let signer = TSigner::new(key, TsigAlgorithm::HmacSha256, Name::from_ascii("ddns-key").unwrap(), 300).unwrap();
+
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 4096];
+            loop {
+                let (len, peer) = socket.recv_from(&mut buf).await.unwrap();
+                let raw = &buf[..len];
+                let request = Message::from_vec(raw).unwrap();
+                let mut response = Message::new();
+                response
+                    .set_id(request.id())
+                    .set_message_type(MessageType::Response)
+                    .set_op_code(request.op_code());
+
+                if request.op_code() == OpCode::Update {
+                    let Ok((request_mac, _, _)) = signer.verify_message_byte(None, raw, true) else {
+                        response.set_response_code(ResponseCode::NotAuth);
+                        socket.send_to(&response.to_vec().unwrap(), peer).await.unwrap();
+                        continue;
+                    };
+                    let mut store = records.lock().unwrap();
+                    for update in request.updates() {
+                        if update.dns_class() == DNSClass::ANY {
+                            store.retain(|r| !(r.name() == update.name() && r.record_type() == update.record_type()));
+                        } else {
+                            store.push(update.clone());
+                        }
+                    }
+                    drop(store);
+
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+                    let pre_tsig = TSIG::new(TsigAlgorithm::HmacSha256, now, 300, Vec::new(), request.id(), 0, Vec::new());
+                    let tbs = message_tbs(Some(&request_mac), &response, &pre_tsig, signer.signer_name()).unwrap();
+                    let mac = signer.sign(&tbs).unwrap();
+                    response.add_additional(make_tsig_record(signer.signer_name().clone(), pre_tsig.set_mac(mac)));
+                } else {
+                    let query = request.queries()[0].clone();
+                    let answers: Vec<Record> = records
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .filter(|r| r.name() == query.name() && r.record_type() == query.query_type())
+                        .cloned()
+                        .collect();
+                    response.add_query(query);
+                    response.add_answers(answers);
+                }
+                socket.send_to(&response.to_vec().unwrap(), peer).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    fn entry(server: &str) -> Entry {
+        Entry {
+            name: "home.example.org".into(),
+            activated: true,
+            txt: None,
+            ipv4: true,
+            ipv6: false,
+            tags: Vec::new(),
+            provider: ProviderConfig::Rfc2136 {
+                server: server.into(),
+                zone: "example.org".into(),
+                key_name: "ddns-key".into(),
+                key_secret: SECRET.into(),
+                algorithm: "hmac-sha256".into(),
+                ttl: 60,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_replaces_rrset() {
+        let records = Arc::new(Mutex::new(vec![Record::from_rdata(
+            Name::from_ascii("home.example.org.").unwrap(),
+            60,
+            RData::A(A("192.0.2.1".parse().unwrap())),
+        )]));
+        let server = spawn_server(records.clone()).await;
+        let client = Rfc2136::new(&server, "example.org", "ddns-key", SECRET, "hmac-sha256", 60, Duration::from_secs(2));
+
+        let addrs = Addresses {
+            ipv4: Some("192.0.2.55".parse().unwrap()),
+            ipv6: Some("2001:db8::55".parse().unwrap()),
+        };
+        let res = client.update(&entry(&server), &addrs).await.unwrap();
+
+        assert!(res.success, "{}", res.detail);
+        let store = records.lock().unwrap();
+        assert_eq!(store.len(), 2);
+        assert_eq!(store[0].data(), &RData::A(A("192.0.2.55".parse().unwrap())));
+        assert_eq!(store[1].data(), &RData::AAAA(AAAA("2001:db8::55".parse().unwrap())));
+    }
+
+    #[tokio::test]
+    async fn test_bad_key_disables_entry() {
+        let server = spawn_server(Arc::new(Mutex::new(Vec::new()))).await;
+        let wrong = base64::engine::general_purpose::STANDARD.encode("not-the-right-key");
+        let client = Rfc2136::new(&server, "example.org", "ddns-key", &wrong, "hmac-sha256", 60, Duration::from_secs(2));
+
+        let addrs = Addresses {
+            ipv4: Some("192.0.2.55".parse().unwrap()),
+            ipv6: None,
+        };
+        let res = client.update(&entry(&server), &addrs).await.unwrap();
+
+        assert!(!res.success);
+        assert_eq!(res.on_failure, FailureAction::Disable);
+    }
+}
@@ -1,22 +1,97 @@
 // src/paths.rs
 use std::path::PathBuf;
 
+use once_cell::sync::OnceCell;
+
+/// Ruta de settings indicada con `--config`, si la hubo.
+static SETTINGS_OVERRIDE: OnceCell<PathBuf> = OnceCell::new();
+
+/// Directorios de config y runtime fijados con `use_system_dirs`.
+static PINNED_DIRS: OnceCell<(PathBuf, PathBuf)> = OnceCell::new();
+
+/// Datos del servicio cuando corre con `run_as`.
+pub const SYSTEM_CONFIG_DIR: &str = "/var/lib/domainhdlr";
+/// Socket y PID file cuando corre con `run_as`.
+pub const SYSTEM_RUNTIME_DIR: &str = "/run/domainhdlr";
+
 pub fn config_dir() -> PathBuf {
+    if let Some((config, _)) = PINNED_DIRS.get() {
+        return config.clone();
+    }
     dirs::config_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join("domainhdlr")
 }
 
+/// Con `run_as` el home de quien lanza el servicio no sirve: config y
+/// runtime pasan a los directorios de sistema para todo el proceso. La
+/// ruta de settings queda fija donde estaba.
+pub fn use_system_dirs() {
+    set_settings_file(settings_file());
+    let _ = PINNED_DIRS.set((PathBuf::from(SYSTEM_CONFIG_DIR), PathBuf::from(SYSTEM_RUNTIME_DIR)));
+}
+
 pub fn config_file() -> PathBuf {
     config_dir().join("domainhdlr.json")
 }
 
-pub fn log_file_error() -> PathBuf {
-    config_dir().join("log_error.txt")
+/// Fija la ruta de settings para todo el proceso. Solo la primera llamada tiene efecto.
+pub fn set_settings_file(path: PathBuf) {
+    let _ = SETTINGS_OVERRIDE.set(path);
+}
+
+/// Ruta explícita de settings, si se pasó `--config`.
+pub fn settings_override() -> Option<&'static PathBuf> {
+    SETTINGS_OVERRIDE.get()
+}
+
+pub fn settings_file() -> PathBuf {
+    settings_override()
+        .cloned()
+        .unwrap_or_else(|| config_dir().join("settings.json"))
+}
+
+/// Directorio de runtime ($XDG_RUNTIME_DIR/domainhdlr), o el de config
+/// si no hay uno (servicios de sistema).
+pub fn runtime_dir() -> PathBuf {
+    if let Some((_, runtime)) = PINNED_DIRS.get() {
+        return runtime.clone();
+    }
+    dirs::runtime_dir()
+        .map(|d| d.join("domainhdlr"))
+        .unwrap_or_else(config_dir)
+}
+
+pub fn control_socket() -> PathBuf {
+    runtime_dir().join("control.sock")
+}
+
+pub fn pid_file() -> PathBuf {
+    runtime_dir().join("domainhdlr.pid")
+}
+
+/// stdout del daemon tras `start --detached`.
+pub fn daemon_out_file() -> PathBuf {
+    config_dir().join("daemon.out.log")
+}
+
+/// stderr del daemon tras `start --detached`.
+pub fn daemon_err_file() -> PathBuf {
+    config_dir().join("daemon.err.log")
 }
 
-pub fn log_file() -> PathBuf {
-    config_dir().join("log.txt")
+/// Estado persistente del daemon (última IP, historial por dominio).
+pub fn state_file() -> PathBuf {
+    config_dir().join("state.json")
+}
+
+/// Directorio del log de eventos y sus archivos rotados.
+pub fn log_dir() -> PathBuf {
+    config_dir().join("logs")
+}
+
+pub fn events_file() -> PathBuf {
+    log_dir().join("events.jsonl")
 }
 pub fn bin_dir() -> PathBuf {
     dirs::home_dir()
@@ -29,9 +104,7 @@ pub fn bin_path() -> PathBuf {
 }
 
 pub fn systemd_user_dir() -> PathBuf {
-    dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("/etc/systemd/system")
+    PathBuf::from("/etc/systemd/system")
 }
 
 pub fn service_path() -> PathBuf {
@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use rand::Rng;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::oneshot;
+use tokio::time::{Instant, sleep, sleep_until};
+
+use crate::models::settings::Settings;
+
+use super::control::ControlCommand;
+use super::file_watch::watch_file;
+use super::rutas::config_file;
+
+/// Tiempo para que se asiente una ráfaga de eventos de red.
+const NETWORK_SETTLE: Duration = Duration::from_secs(2);
+
+/// Tiempo para que termine de escribirse `domainhdlr.json`.
+const CONFIG_SETTLE: Duration = Duration::from_millis(300);
+
+/// Motivo por el que el scheduler despierta al loop.
+#[derive(Debug)]
+pub enum Wake {
+    /// Venció el intervalo de sondeo.
+    Timer,
+    /// Reintento tras un ciclo fallido.
+    Retry,
+    /// El kernel informó un cambio de direcciones o de ruta por defecto.
+    NetworkChange(String),
+    /// Se modificó `domainhdlr.json`.
+    ConfigChanged,
+    /// Se pidió detener el servicio.
+    Stop,
+    /// Se pidió reiniciar el proceso.
+    Restart,
+    /// Releer settings y dominios; si hay canal, se responde el resultado.
+    Reload(Option<oneshot::Sender<Result<(), String>>>),
+}
+
+/// Espera exponencial con jitter: `base * 2^(n-1)`, tope `max`,
+/// multiplicado por un factor aleatorio en [0.5, 1.0).
+pub fn backoff_delay(base: Duration, max: Duration, failures: u32) -> Duration {
+    let exp = base.saturating_mul(1u32 << failures.saturating_sub(1).min(16));
+    let capped = exp.min(max);
+    capped.mul_f64(rand::rng().random_range(0.5..1.0))
+}
+
+pub struct Scheduler {
+    poll: Duration,
+    retry_base: Duration,
+    retry_max: Duration,
+    events: Option<UnboundedReceiver<String>>,
+    config: Option<UnboundedReceiver<()>>,
+    commands: UnboundedReceiver<ControlCommand>,
+}
+
+impl Scheduler {
+    pub fn new(settings: &Settings, commands: UnboundedReceiver<ControlCommand>) -> Self {
+        let events = match watch_network_changes() {
+            Ok(rx) => Some(rx),
+            Err(e) => {
+                tracing::warn!("Network change events unavailable, polling only: {}", e);
+                None
+            }
+        };
+        let config = match watch_file(&config_file()) {
+            Ok(rx) => Some(rx),
+            Err(e) => {
+                tracing::warn!("Cannot watch {}, changes apply on the next cycle: {}", config_file().display(), e);
+                None
+            }
+        };
+        Self {
+            poll: Duration::from_millis(settings.poll_interval_ms),
+            retry_base: Duration::from_millis(settings.retry_base_ms),
+            retry_max: Duration::from_millis(settings.retry_max_ms),
+            events,
+            config,
+            commands,
+        }
+    }
+
+    /// Aplica intervalos nuevos tras un reload.
+    pub fn reconfigure(&mut self, settings: &Settings) {
+        self.poll = Duration::from_millis(settings.poll_interval_ms);
+        self.retry_base = Duration::from_millis(settings.retry_base_ms);
+        self.retry_max = Duration::from_millis(settings.retry_max_ms);
+    }
+
+    /// Espera hasta el próximo ciclo. Con `failures > 0` usa backoff
+    /// en lugar del intervalo de sondeo.
+    pub async fn wait(&mut self, failures: u32) -> Wake {
+        let (delay, on_timeout) = if failures > 0 {
+            let delay = backoff_delay(self.retry_base, self.retry_max, failures);
+            (delay, Wake::Retry)
+        } else {
+            (self.poll, Wake::Timer)
+        };
+        let deadline = Instant::now() + delay;
+
+        tokio::select! {
+            _ = sleep_until(deadline) => on_timeout,
+            command = self.commands.recv() => match command {
+                Some(ControlCommand::Restart) => Wake::Restart,
+                Some(ControlCommand::Reload(reply)) => Wake::Reload(reply),
+                // Sin canal de control no hay forma de seguir controlando el daemon
+                Some(ControlCommand::Stop) | None => Wake::Stop,
+            },
+            Some(reason) = next_event(&mut self.events) => {
+                sleep(NETWORK_SETTLE).await;
+                if let Some(rx) = self.events.as_mut() {
+                    while rx.try_recv().is_ok() {}
+                }
+                Wake::NetworkChange(reason)
+            }
+            Some(()) = next_event(&mut self.config) => {
+                sleep(CONFIG_SETTLE).await;
+                if let Some(rx) = self.config.as_mut() {
+                    while rx.try_recv().is_ok() {}
+                }
+                Wake::ConfigChanged
+            }
+        }
+    }
+}
+
+async fn next_event<T>(events: &mut Option<UnboundedReceiver<T>>) -> Option<T> {
+    match events {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Se suscribe a los grupos multicast de rtnetlink de direcciones y rutas.
+#[cfg(target_os = "linux")]
+pub fn watch_network_changes() -> std::io::Result<UnboundedReceiver<String>> {
+    use futures::StreamExt;
+    use netlink_packet_core::NetlinkPayload;
+    use netlink_packet_route::RtnlMessage;
+    use netlink_sys::{AsyncSocket, SocketAddr};
+    use rtnetlink::constants::{RTMGRP_IPV4_IFADDR, RTMGRP_IPV4_ROUTE, RTMGRP_IPV6_IFADDR, RTMGRP_IPV6_ROUTE};
+
+    let (mut connection, handle, mut messages) = rtnetlink::new_connection()?;
+    let groups = RTMGRP_IPV4_IFADDR | RTMGRP_IPV6_IFADDR | RTMGRP_IPV4_ROUTE | RTMGRP_IPV6_ROUTE;
+    connection
+        .socket_mut()
+        .socket_mut()
+        .bind(&SocketAddr::new(0, groups))?;
+    tokio::spawn(connection);
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        // La conexión vive mientras exista el handle
+        let _handle = handle;
+        while let Some((msg, _)) = messages.next().await {
+            let NetlinkPayload::InnerMessage(msg) = msg.payload else {
+                continue;
+            };
+            let reason = match msg {
+                RtnlMessage::NewAddress(_) => "address added",
+                RtnlMessage::DelAddress(_) => "address removed",
+                RtnlMessage::NewRoute(r) if r.header.destination_prefix_length == 0 => "default route added",
+                RtnlMessage::DelRoute(r) if r.header.destination_prefix_length == 0 => "default route removed",
+                _ => continue,
+            };
+            if tx.send(reason.to_string()).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn watch_network_changes() -> std::io::Result<UnboundedReceiver<String>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "network change events require Linux (rtnetlink)",
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let base = Duration::from_secs(5);
+        let max = Duration::from_secs(60);
+        for _ in 0..50 {
+            let first = backoff_delay(base, max, 1);
+            assert!(first >= Duration::from_millis(2500) && first < base);
+            let third = backoff_delay(base, max, 3);
+            assert!(third >= Duration::from_secs(10) && third < Duration::from_secs(20));
+            assert!(backoff_delay(base, max, 30) < max);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::fs;
+use std::io;
+
+use crate::models::settings::{IpDetectionSettings, IpSourceConfig, SETTINGS_VERSION, Settings};
+
+use super::rutas::settings_file;
+
+/// Lee `settings.json`. Si no existe se crea con valores por defecto;
+/// si es de una versión anterior se migra y se vuelve a guardar.
+pub fn load_settings() -> Result<Settings, Box<dyn std::error::Error + Send + Sync>> {
+    let path = settings_file();
+    if !path.exists() {
+        let settings = Settings::default();
+        save_settings(&settings)?;
+        println!("Settings file created at {}", path.display());
+        return Ok(settings);
+    }
+
+    let content = fs::read_to_string(&path)?;
+    let mut settings: Settings = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid settings file {}: {}", path.display(), e))?;
+
+    if settings.version > SETTINGS_VERSION {
+        return Err(format!(
+            "Settings file {} has version {}, this binary supports up to {}",
+            path.display(),
+            settings.version,
+            SETTINGS_VERSION
+        )
+        .into());
+    }
+    if settings.version < SETTINGS_VERSION {
+        migrate(&mut settings);
+        save_settings(&settings)?;
+    }
+    validate(&settings).map_err(|e| format!("Invalid settings file {}: {}", path.display(), e))?;
+    Ok(settings)
+}
+
+/// Valores que el scheduler no puede usar.
+fn validate(settings: &Settings) -> Result<(), String> {
+    if settings.poll_interval_ms == 0 {
+        // Con 0 el loop no espera nunca entre ciclos
+        return Err("poll_interval_ms must be greater than 0".into());
+    }
+    Ok(())
+}
+
+/// `run_as` del archivo de settings, sin crearlo ni migrarlo.
+pub fn configured_run_as() -> Option<String> {
+    let content = fs::read_to_string(settings_file()).ok()?;
+    serde_json::from_str::<Settings>(&content).ok()?.run_as
+}
+
+fn migrate(settings: &mut Settings) {
+    // v2: el loop pasó a un scheduler; el sondeo de 100 ms ya no tiene sentido
+    if settings.version < 2 && settings.poll_interval_ms < 1000 {
+        settings.poll_interval_ms = Settings::default().poll_interval_ms;
+    }
+    // v3: la IPv6 tiene su propia lista; las interfaces con `ipv6` se mudan ahí
+    if settings.version < 3 {
+        let (v6, v4): (Vec<_>, Vec<_>) = settings
+            .ip_detection
+            .sources
+            .drain(..)
+            .partition(|s| matches!(s, IpSourceConfig::Interface { ipv6: true, .. }));
+        settings.ip_detection.sources = v4;
+        if !v6.is_empty() {
+            settings.ipv6_detection.sources.splice(0..0, v6);
+        }
+        if settings.ip_detection.sources.is_empty() {
+            settings.ip_detection = IpDetectionSettings::default();
+        }
+    }
+    settings.version = SETTINGS_VERSION;
+}
+
+pub fn save_settings(settings: &Settings) -> io::Result<()> {
+    let path = settings_file();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(settings)?;
+    fs::write(&path, json)?;
+
+    // Contiene la clave SMTP: solo legible por el dueño.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_migrate_moves_ipv6_interfaces() {
+        let mut settings: Settings = serde_json::from_str(
+            r#"{"version": 2, "ip_detection": {"sources": [
+                {"type": "interface", "name": "eth0", "ipv6": true}
+            ], "quorum": 1}}"#,
+        )
+        .unwrap();
+        migrate(&mut settings);
+
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert!(matches!(
+            &settings.ipv6_detection.sources[0],
+            IpSourceConfig::Interface { name, ipv6: true } if name == "eth0"
+        ));
+        assert_eq!(settings.ip_detection.sources.len(), IpDetectionSettings::default().sources.len());
+    }
+
+    #[test]
+    fn test_file_without_version_is_migrated() {
+        let mut settings: Settings = serde_json::from_str(r#"{"poll_interval_ms": 100}"#).unwrap();
+        assert_eq!(settings.version, 1);
+        migrate(&mut settings);
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.poll_interval_ms, Settings::default().poll_interval_ms);
+    }
+
+    #[test]
+    fn test_validate_rejects_zero_poll_interval() {
+        let mut settings = Settings::default();
+        assert!(validate(&settings).is_ok());
+        settings.poll_interval_ms = 0;
+        assert!(validate(&settings).is_err());
+    }
+}
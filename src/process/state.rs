@@ -0,0 +1,80 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::models::state::DaemonState;
+
+use super::rutas::state_file;
+
+/// Lee `state.json`. Sin archivo, o si está dañado, se empieza de cero:
+/// en el peor caso se vuelven a actualizar todos los dominios.
+pub fn load_state() -> DaemonState {
+    match load_state_from(&state_file()) {
+        Ok(state) => state,
+        Err(e) => {
+            if e.kind() != io::ErrorKind::NotFound {
+                tracing::warn!("Ignoring state file {}: {}", state_file().display(), e);
+            }
+            DaemonState::default()
+        }
+    }
+}
+
+fn load_state_from(path: &Path) -> io::Result<DaemonState> {
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn save_state(state: &DaemonState) -> io::Result<()> {
+    save_state_to(&state_file(), state)
+}
+
+/// Escribe a un temporal y renombra, para no dejar un archivo a medias
+/// si el proceso muere en el medio.
+fn save_state_to(path: &Path, state: &DaemonState) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(state)?)?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::state::DomainState;
+
+    #[test]
+    fn test_state_round_trip() {
+        let path = std::env::temp_dir().join(format!("domainhdlr-state-{}.json", std::process::id()));
+        let mut state = DaemonState {
+            last_ip: "203.0.113.5".into(),
+            ..DaemonState::default()
+        };
+        let mut casa = DomainState::default();
+        casa.record_error("timeout".into(), "ayer".into());
+        casa.record_success("203.0.113.5".into(), "3f2a".into(), "hoy".into());
+        state.domains.insert("casa".into(), casa);
+
+        save_state_to(&path, &state).unwrap();
+        let loaded = load_state_from(&path).unwrap();
+        assert_eq!(loaded, state);
+        assert_eq!(loaded.domains["casa"].failures, 0);
+        assert_eq!(loaded.domains["casa"].config_hash.as_deref(), Some("3f2a"));
+        assert_eq!(loaded.domains["casa"].last_error.as_deref(), Some("timeout"));
+
+        fs::write(&path, "{ roto").unwrap();
+        assert!(load_state_from(&path).is_err());
+        let _ = fs::remove_file(&path);
+
+        // X publicado, Y falló (quizás el proveedor lo aplicó), vuelve X:
+        // hay que empujar X de nuevo
+        let mut casa = DomainState::default();
+        casa.record_success("203.0.113.5".into(), "3f2a".into(), "lunes".into());
+        assert!(casa.is_current("203.0.113.5", "3f2a"));
+        casa.record_error("timeout".into(), "martes".into());
+        assert!(!casa.is_current("203.0.113.5", "3f2a"));
+        assert!(!casa.is_current("198.51.100.7", "3f2a"));
+    }
+}
@@ -0,0 +1,112 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::process::http_client::http_client;
+use crate::process::notifier::{Alert, Notifier, NotifyResult};
+
+pub const TELEGRAM_API: &str = "https://api.telegram.org";
+
+/// Respuesta de la Bot API.
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    ok: bool,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+/// Canal que manda la alerta como mensaje de un bot a un chat.
+pub struct Telegram {
+    client: Client,
+    name: String,
+    api_base: String,
+    bot_token: String,
+    chat_id: String,
+}
+
+impl Telegram {
+    pub fn new(name: &str, bot_token: &str, chat_id: &str, api_base: Option<&str>) -> Self {
+        Self {
+            client: http_client().clone(),
+            name: name.to_string(),
+            api_base: api_base.unwrap_or(TELEGRAM_API).trim_end_matches('/').to_string(),
+            bot_token: bot_token.to_string(),
+            chat_id: chat_id.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for Telegram {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send(&self, alert: &Alert) -> NotifyResult<()> {
+        let url = format!("{}/bot{}/sendMessage", self.api_base, self.bot_token);
+        // El token va en la URL: no debe aparecer en los errores
+        let res: ApiResponse = self
+            .client
+            .post(&url)
+            .json(&json!({ "chat_id": self.chat_id, "text": alert.text() }))
+            .send()
+            .await
+            .map_err(|e| e.without_url())?
+            .json()
+            .await
+            .map_err(|e| e.without_url())?;
+        if !res.ok {
+            return Err(format!(
+                "Telegram rejected the message: {}",
+                res.description.as_deref().unwrap_or("no description")
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::settings::AlertKind;
+    use mockito::Matcher;
+
+    #[tokio::test]
+    async fn test_send_message_and_api_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let sent = server
+            .mock("POST", "/botsecreto/sendMessage")
+            .match_body(Matcher::Json(json!({
+                "chat_id": "-100123",
+                "text": "⚠️ DNS desincronizado para casa\nNo apunta a 203.0.113.5",
+            })))
+            .with_body(r#"{"ok":true,"result":{}}"#)
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/botmalo/sendMessage")
+            .with_status(401)
+            .with_body(r#"{"ok":false,"error_code":401,"description":"Unauthorized"}"#)
+            .create_async()
+            .await;
+
+        let alert = Alert::new(
+            AlertKind::DnsMismatch,
+            "⚠️ DNS desincronizado para casa",
+            "No apunta a 203.0.113.5",
+        );
+        Telegram::new("chat", "secreto", "-100123", Some(&server.url()))
+            .send(&alert)
+            .await
+            .unwrap();
+        sent.assert_async().await;
+
+        let err = Telegram::new("chat", "malo", "-100123", Some(&server.url()))
+            .send(&alert)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Telegram rejected the message: Unauthorized");
+    }
+}
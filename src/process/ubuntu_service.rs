@@ -5,7 +5,7 @@ use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::process::Command;
 
-use crate::process::rutas::{bin_dir, bin_path, config_dir, config_file, service_path, systemd_user_dir};
+use crate::process::rutas::{bin_dir, bin_path, config_dir, config_file, service_path, settings_override, systemd_user_dir};
 pub fn install_service() -> anyhow::Result<()> {
     match fs::create_dir_all(bin_dir()) {
         Ok(_) => println!("[OK] Created bin dir"),
@@ -31,13 +31,13 @@ pub fn install_service() -> anyhow::Result<()> {
     };
 
     if this_exe.is_file() {
-        match fs::copy(&this_exe, &bin_path()) {
+        match fs::copy(&this_exe, bin_path()) {
             Ok(_) => println!("[OK] Copied binary"),
             Err(e) => eprintln!("[ERR] Copying binary: {e}"),
         }
 
         #[cfg(target_os = "linux")]
-        match fs::set_permissions(&bin_path(), fs::Permissions::from_mode(0o755)) {
+        match fs::set_permissions(bin_path(), fs::Permissions::from_mode(0o755)) {
             Ok(_) => println!("[OK] Set permissions on binary"),
             Err(e) => eprintln!("[ERR] Setting binary permissions: {e}"),
         }
@@ -49,13 +49,13 @@ pub fn install_service() -> anyhow::Result<()> {
     }
 
     if Path::new("domainhdlr.json").exists() {
-        match fs::copy("domainhdlr.json", &config_file()) {
+        match fs::copy("domainhdlr.json", config_file()) {
             Ok(_) => println!("[OK] Copied config file"),
             Err(e) => eprintln!("[ERR] Copying config file: {e}"),
         }
 
         #[cfg(target_os = "linux")]
-        match fs::set_permissions(&config_file(), fs::Permissions::from_mode(0o644)) {
+        match fs::set_permissions(config_file(), fs::Permissions::from_mode(0o644)) {
             Ok(_) => println!("[OK] Set permissions on config file"),
             Err(e) => eprintln!("[ERR] Setting config permissions: {e}"),
         }
@@ -94,13 +94,17 @@ pub fn install_service() -> anyhow::Result<()> {
         }
     }
 
+    let config_arg = settings_override()
+        .map(|p| format!(" --config {}", p.to_string_lossy()))
+        .unwrap_or_default();
+
     let service_content = format!(
         r#"[Unit]
 Description=Domain Handler Service for DuckDNS
 After=network.target
 
 [Service]
-ExecStart={} start
+ExecStart={}{} start
 Restart=on-failure
 User={}
 WorkingDirectory={}
@@ -109,6 +113,7 @@ WorkingDirectory={}
 WantedBy=default.target
 "#,
         bin_path().to_string_lossy(),
+        config_arg,
         whoami::username(),
         bin_dir().to_string_lossy()
     );
@@ -156,14 +161,14 @@ pub fn uninstall_service() -> anyhow::Result<()> {
         Err(e) => eprintln!("[ERR] Removing config file: {e}"),
     }
 
-    if fs::read_dir(config_dir()).map_or(false, |mut d| d.next().is_none()) {
+    if fs::read_dir(config_dir()).is_ok_and(|mut d| d.next().is_none()) {
         match fs::remove_dir(config_dir()) {
             Ok(_) => println!("[OK] Removed config dir"),
             Err(e) => eprintln!("[ERR] Removing config dir: {e}"),
         }
     }
 
-    if fs::read_dir(bin_dir()).map_or(false, |mut d| d.next().is_none()) {
+    if fs::read_dir(bin_dir()).is_ok_and(|mut d| d.next().is_none()) {
         match fs::remove_dir(bin_dir()) {
             Ok(_) => println!("[OK] Removed bin dir"),
             Err(e) => eprintln!("[ERR] Removing bin dir: {e}"),
@@ -0,0 +1,97 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{Value, json};
+
+use crate::models::settings::WebhookFormat;
+use crate::process::http_client::http_client;
+use crate::process::notifier::{Alert, Notifier, NotifyResult};
+
+/// Canal que hace POST de la alerta como JSON. Las URLs de Slack y
+/// Discord llevan el secreto, por eso no aparecen en los errores.
+pub struct Webhook {
+    client: Client,
+    name: String,
+    url: String,
+    format: WebhookFormat,
+}
+
+impl Webhook {
+    pub fn new(name: &str, url: &str, format: WebhookFormat) -> Self {
+        Self {
+            client: http_client().clone(),
+            name: name.to_string(),
+            url: url.to_string(),
+            format,
+        }
+    }
+
+    fn payload(&self, alert: &Alert) -> NotifyResult<Value> {
+        Ok(match self.format {
+            WebhookFormat::Json => serde_json::to_value(alert)?,
+            WebhookFormat::Slack => json!({ "text": alert.text() }),
+            WebhookFormat::Discord => json!({ "content": alert.text() }),
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for Webhook {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn send(&self, alert: &Alert) -> NotifyResult<()> {
+        self.client
+            .post(&self.url)
+            .json(&self.payload(alert)?)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| e.without_url())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::settings::AlertKind;
+    use mockito::Matcher;
+
+    #[tokio::test]
+    async fn test_slack_and_json_payloads() {
+        let mut server = mockito::Server::new_async().await;
+        let slack = server
+            .mock("POST", "/slack")
+            .match_body(Matcher::Json(json!({ "text": "⛔ Dominio casa desactivado\nToken inválido" })))
+            .create_async()
+            .await;
+        let generic = server
+            .mock("POST", "/hook")
+            .match_body(Matcher::PartialJson(json!({
+                "kind": "entry_disabled",
+                "severity": "error",
+                "domain": "casa",
+                "tags": ["prod"],
+            })))
+            .create_async()
+            .await;
+        server.mock("POST", "/caido").with_status(500).create_async().await;
+
+        let mut alert = Alert::new(AlertKind::EntryDisabled, "⛔ Dominio casa desactivado", "Token inválido");
+        alert.domain = Some("casa".into());
+        alert.tags = vec!["prod".into()];
+
+        let url = |path: &str| format!("{}{}", server.url(), path);
+        Webhook::new("chat", &url("/slack"), WebhookFormat::Slack).send(&alert).await.unwrap();
+        Webhook::new("hook", &url("/hook"), WebhookFormat::Json).send(&alert).await.unwrap();
+        let err = Webhook::new("caido", &url("/caido"), WebhookFormat::Discord)
+            .send(&alert)
+            .await
+            .unwrap_err();
+        assert!(!err.to_string().contains("/caido"), "{}", err);
+
+        slack.assert_async().await;
+        generic.assert_async().await;
+    }
+}
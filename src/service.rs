@@ -1,84 +1,272 @@
-use sysinfo::System;
+use std::error::Error;
+use std::io::Write;
+use std::time::Duration;
 
-use crate::process::{file_lock::{create_lock_file, get_lock_path, remove_cfg_file}, loop_proc::run_loop, notifier::{MailConfig, send_email_alert}};
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::time::{Instant, sleep};
+use tracing::{info, warn};
 
+use crate::models::settings::{AlertKind, Settings};
+use crate::models::state::DaemonState;
+use crate::process::{
+    control::{
+        ControlCommand, ControlRequest, DaemonStatus, command_channel, is_not_running, new_status, remove_socket,
+        send_request, serve,
+    },
+    daemon::{notify_ready, remove_pid_file, write_pid_file},
+    domains::read_domains,
+    file_lock::{EXIT_ALREADY_RUNNING, InstanceLock, LockError},
+    log_layer::enable_event_log,
+    logger::{configure as configure_log, flush_sinks},
+    loop_proc::{LoopExit, run_loop},
+    notifier::{Alert, notify},
+    state::load_state,
+};
 
+/// Cuánto se espera a que el daemon termine o vuelva a estar listo.
+const DAEMON_WAIT: Duration = Duration::from_secs(30);
+const POLL: Duration = Duration::from_millis(200);
 
+/// Historial por dominio: el que tiene el daemon en memoria o, si no
+/// corre, el último guardado en `state.json`.
+fn current_state(running: Option<&DaemonStatus>) -> DaemonState {
+    running.map(|s| s.state.clone()).unwrap_or_else(load_state)
+}
+
+/// Estado del daemon, o `None` si no hay ninguno escuchando.
+async fn daemon_status() -> Option<DaemonStatus> {
+    match send_request(ControlRequest::Status).await {
+        Ok(r) if r.ok => r.status,
+        _ => None,
+    }
+}
 
 #[allow(unused)]
-pub async fn stop(cfg: &MailConfig) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    //Force kill
-    let mut s = System::new();
-    s.refresh_processes(sysinfo::ProcessesToUpdate::All, false);
-
-    for (pid, proc_name) in s.processes().iter() {
-
-        if proc_name.name() == "domainhdlr" {
-            let s = proc_name.kill_and_wait();
-            if let Err(kill) = s {
-                let body = format!("No se pudo matar la instancia con PID: {}",pid.as_u32());
-                send_email_alert(cfg, "Kill error", "");
-            }else{
-                println!("Killed pid -> {}",pid.as_u32())
-            }
+pub async fn stop(settings: &Settings) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match send_request(ControlRequest::Stop).await {
+        Ok(r) if r.ok => {}
+        Ok(r) => return Err(r.message.into()),
+        Err(e) if is_not_running(&e) => {
+            println!("Service is not running");
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    }
 
+    // El daemon termina el ciclo en curso y cierra el socket
+    let deadline = Instant::now() + DAEMON_WAIT;
+    while Instant::now() < deadline {
+        if daemon_status().await.is_none() {
+            println!("Service stopped");
+            return Ok(());
         }
+        sleep(POLL).await;
     }
-    remove_cfg_file().await;
-    Ok(())
+    let body = format!(
+        "El servicio no terminó en {} s tras pedirle que se detenga.\nHora: {}",
+        DAEMON_WAIT.as_secs(),
+        settings.now_str()
+    );
+    let _ = notify(settings, Alert::new(AlertKind::StopError, "Stop error", body)).await;
+    Err("Service did not stop in time".into())
 }
 
 #[allow(unused)]
-pub fn status() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-
-    let mut s = System::new();
-    s.refresh_processes(sysinfo::ProcessesToUpdate::All, false);
-
+pub async fn status() -> Result<(), Box<dyn Error + Send + Sync>> {
     // Imprimimos el encabezado
     println!("### Estado del Sistema ###");
     println!("=========================");
-    println!("Estado File: existe [{}]",get_lock_path().unwrap().exists());
+
+    let running = daemon_status().await;
+    match &running {
+        Some(s) => {
+            println!("Estado: Corriendo");
+            println!("PID: {}", s.pid);
+            println!("Iniciado: {} (hace {} s)", s.started_at, s.uptime_secs);
+            println!("IP pública: {}", if s.public_ip.is_empty() { "-" } else { &s.public_ip });
+            println!("Último ciclo: {}", s.last_cycle.as_deref().unwrap_or("-"));
+            println!("Fallos seguidos: {}", s.failures);
+            println!("Dominios: {} ({} activos)", s.domains, s.active_domains);
+        }
+        None => println!("El servicio no está corriendo"),
+    }
+
+    let state = current_state(running.as_ref());
+    println!("Última IP detectada: {}", if state.last_ip.is_empty() { "-" } else { &state.last_ip });
+    for (name, d) in &state.domains {
+        println!(
+            "- {}: {} (último éxito: {}, fallos: {})",
+            name,
+            d.last_pushed.as_deref().unwrap_or("-"),
+            d.last_success.as_deref().unwrap_or("nunca"),
+            d.failures
+        );
+    }
+
     println!("=========================");
+    Ok(())
+}
+
+/// Muestra la configuración y el historial guardado de un dominio.
+pub async fn domain_status(name: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let entries = read_domains()?;
+    let Some(entry) = entries.iter().find(|e| e.name == name) else {
+        return Err(format!("Domain '{}' not found", name).into());
+    };
+    let state = current_state(daemon_status().await.as_ref());
+    let d = state.domains.get(name).cloned().unwrap_or_default();
 
-    let mut found = false;  // Para verificar si encontramos el proceso
+    println!("### {} ###", entry.fqdn());
+    print!("{}", entry);
+    println!("IP publicada: {}", d.last_pushed.as_deref().unwrap_or("-"));
+    println!("Último éxito: {}", d.last_success.as_deref().unwrap_or("nunca"));
+    match (&d.last_error, &d.last_error_at) {
+        (Some(e), Some(at)) => println!("Último error: {} ({})", e, at),
+        (Some(e), None) => println!("Último error: {}", e),
+        _ => println!("Último error: -"),
+    }
+    println!("Fallos seguidos: {}", d.failures);
+    Ok(())
+}
 
-    // Iteramos sobre los procesos para encontrar "domainhdlr"
-    for (pid, proc) in s.processes().iter() {
+/// Pide al daemon que relea settings y dominios.
+pub async fn reload() -> Result<(), Box<dyn Error + Send + Sync>> {
+    match send_request(ControlRequest::Reload).await {
+        Ok(r) if r.ok => {
+            println!("Service reloaded");
+            Ok(())
+        }
+        Ok(r) => Err(r.message.into()),
+        Err(e) if is_not_running(&e) => Err("Service is not running".into()),
+        Err(e) => Err(e.into()),
+    }
+}
 
-        if proc.name() == "domainhdlr" {
-            found = true;
+/// Reinicia el daemon y espera a que la instancia nueva responda.
+/// Si no hay ninguno corriendo, arranca uno en primer plano.
+pub async fn restart(settings: &Settings) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let Some(before) = daemon_status().await else {
+        return start(settings).await;
+    };
+    let r = send_request(ControlRequest::Restart).await?;
+    if !r.ok {
+        return Err(r.message.into());
+    }
 
-            // Si encontramos el proceso, mostramos la información
-            println!("Proceso 'domainhdlr' encontrado:");
-            println!("PID: {}", pid);
-            println!("Nombre: {}", proc.name().to_str().unwrap_or(""));
+    let deadline = Instant::now() + DAEMON_WAIT;
+    while Instant::now() < deadline {
+        if let Some(now) = daemon_status().await
+            && now.started_ms != before.started_ms
+        {
+            println!("Service restarted (pid {})", now.pid);
+            return Ok(());
+        }
+        sleep(POLL).await;
+    }
+    Err("Service did not come back after restart".into())
+}
 
-            let status = if proc.exists() {
-                "Corriendo"
-            } else {
-                "Detenido"
+#[allow(unused)]
+pub async fn start(settings: &Settings) -> Result<(), Box<dyn Error + Send + Sync>> {
+    configure_log(&settings.log);
+    enable_event_log();
+    let started_at = settings.now_str();
+    let lock = match InstanceLock::acquire(&started_at) {
+        Ok(lock) => lock,
+        Err(e) => {
+            let code = match e {
+                LockError::AlreadyRunning(_) => EXIT_ALREADY_RUNNING,
+                LockError::Io(_) => 1,
             };
-
-            println!("Estado: {}", status);
-            println!("Uso de CPU: {:.2}%", proc.cpu_usage());
-            println!("Uso de Memoria: {:.2} MB", proc.memory() as f64 / 1024.0);
-            println!("------------------------");
+            notify_ready(Err((code, e.to_string())));
+            return Err(e.into());
         }
+    };
+    if let Some(old) = &lock.recovered {
+        warn!(
+            "Recovered stale lock from pid {} (started {}); it did not shut down cleanly",
+            old.pid, old.started_at
+        );
+    }
+    if let Err(e) = write_pid_file() {
+        warn!("Could not write PID file: {}", e);
     }
+    let status = new_status(started_at);
+    let (tx, commands) = command_channel();
+    if let Err(e) = serve(status.clone(), tx.clone()).and_then(|_| forward_signals(tx)) {
+        notify_ready(Err((1, e.to_string())));
+        return Err(e.into());
+    }
+    info!("Service started (pid {})", std::process::id());
+    notify_ready(Ok(()));
 
-    if !found {
-        println!("No se encontró el proceso 'domainhdlr'");
+    let exit = run_loop(settings, status, commands).await;
+    remove_socket();
+    remove_pid_file();
+    if exit == LoopExit::Stop {
+        info!("Service stopping (pid {})", std::process::id());
+        let body = format!(
+            "El servicio domainhdlr se detuvo (pid {}).\nHora: {}",
+            std::process::id(),
+            settings.now_str()
+        );
+        let _ = notify(settings, Alert::new(AlertKind::ServiceStopping, "ℹ️ Servicio detenido", body)).await;
     }
+    let _ = std::io::stdout().flush();
+    flush_sinks();
+    // `exec` no corre destructores: soltar el lock antes
+    drop(lock);
 
-    println!("=========================");
+    if exit == LoopExit::Restart {
+        return Err(exec_self());
+    }
     Ok(())
 }
 
+/// SIGTERM/SIGINT piden detenerse al terminar el ciclo en curso y SIGHUP
+/// relee la configuración. Una segunda SIGTERM/SIGINT sale de inmediato.
+fn forward_signals(tx: UnboundedSender<ControlCommand>) -> std::io::Result<()> {
+    let mut term = signal(SignalKind::terminate())?;
+    let mut int = signal(SignalKind::interrupt())?;
+    let mut hup = signal(SignalKind::hangup())?;
 
-#[allow(unused)]
-pub async fn start() ->  Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    create_lock_file().await;
-    run_loop().await;
-    println!("Service started in background.");
+    tokio::spawn(async move {
+        let mut stopping = false;
+        loop {
+            let name = tokio::select! {
+                _ = term.recv() => "SIGTERM",
+                _ = int.recv() => "SIGINT",
+                _ = hup.recv() => {
+                    info!("Received SIGHUP, reloading configuration");
+                    let _ = tx.send(ControlCommand::Reload(None));
+                    continue;
+                }
+            };
+            if stopping {
+                warn!("Received {} again, exiting immediately", name);
+                std::process::exit(130);
+            }
+            stopping = true;
+            info!("Received {}, stopping after the current cycle", name);
+            let _ = tx.send(ControlCommand::Stop);
+        }
+    });
     Ok(())
 }
+
+/// Reemplaza el proceso por el mismo binario con los mismos argumentos.
+/// Solo vuelve si `exec` falla.
+fn exec_self() -> Box<dyn Error + Send + Sync> {
+    use std::os::unix::process::CommandExt;
+
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => return e.into(),
+    };
+    info!("Restarting {}", exe.display());
+    std::process::Command::new(exe)
+        .args(std::env::args_os().skip(1))
+        .exec()
+        .into()
+}